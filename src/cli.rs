@@ -0,0 +1,331 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use rust_tracer::render::{Aov, FilterKind, IntegratorKind, TileOrder, ToneMap};
+use rust_tracer::util::sampler::SamplerKind;
+
+pub const USAGE: &str = "\
+Usage: rust_tracer [OPTIONS] [SCENE]
+
+SCENE is the name of a built-in scene or the path of a scene file
+(default: marble_land, or the scene of the checkpoint given to --resume).
+
+Options:
+  -o, --output <PATH>     output image, .png, .exr or .hdr (default: out.png)
+      --half              write EXR images with half floats
+      --tonemap <OP>      clamp, reinhard or aces for PNG output
+      --exposure <EV>     exposure adjustment in stops for PNG output
+      --dither            dither PNG output before quantizing to 8 bits
+      --denoise           filter out remaining noise, guided by the albedo,
+                          normal and depth of the first hits
+      --aov <NAMES>       also write albedo, normal, depth, object_id and/or
+                          material_id (comma separated, or 'all'); EXR output
+                          stores them as extra channels
+  -W, --width <N>         image width in pixels
+  -H, --height <N>        image height in pixels
+  -s, --spp <N>           samples per pixel
+  -d, --max-depth <N>     maximum ray bounces
+      --integrator <I>    path, direct, ao (ambient occlusion) or a debug
+                          view: normals, uv, distance, bvh_cost or
+                          material_id (default: path)
+      --ao-radius <R>     distance within which geometry occludes for ao
+                          (default: unlimited)
+      --adaptive <T>      stop sampling a pixel once its 95% confidence
+                          interval is within T times its brightness; --spp
+                          becomes the upper limit
+      --min-spp <N>       samples every pixel takes before it may stop
+                          (default: 16)
+      --heatmap <PATH>    also write an image of the samples each pixel took
+  -t, --threads <N>       worker threads (default: available cores)
+      --seed <N>          random seed
+      --sampler <S>       random, stratified, halton or sobol (default: sobol)
+      --filter <F>        pixel filter: box, tent, gaussian, mitchell or
+                          lanczos (default: box)
+      --filter-radius <R> filter radius in pixels (default: depends on the
+                          filter)
+      --tile-size <N>     edge length of render tiles (default: 32)
+      --tile-order <O>    spiral or scanline (default: spiral)
+  -p, --progressive       refine the whole image in passes of 1, 2, 4, ... spp
+      --preview <PATH>    rewrite a preview image while rendering progressively
+      --preview-every <S> seconds between preview updates (default: 10)
+      --preview-passes <N>
+                          also update the preview every N passes
+      --checkpoint <PATH> save progress here periodically and on Ctrl-C
+      --checkpoint-every <S>
+                          seconds between checkpoints (default: 300)
+      --resume <PATH>     continue the render saved in a checkpoint; only
+                          --threads and --spp override its settings
+  -h, --help              print this message";
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub scene: Option<String>,
+    pub output: PathBuf,
+    pub half: bool,
+    pub tone_map: Option<ToneMap>,
+    pub exposure: Option<f64>,
+    pub dither: bool,
+    pub denoise: bool,
+    pub aovs: Vec<Aov>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples_per_pixel: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub integrator: Option<IntegratorKind>,
+    pub ao_radius: Option<f64>,
+    pub adaptive: Option<f64>,
+    pub min_samples: Option<usize>,
+    pub heatmap: Option<PathBuf>,
+    pub threads: usize,
+    pub seed: Option<u64>,
+    pub sampler: Option<SamplerKind>,
+    pub filter: Option<FilterKind>,
+    pub filter_radius: Option<f64>,
+    pub tile_size: Option<usize>,
+    pub tile_order: Option<TileOrder>,
+    pub progressive: bool,
+    pub preview: Option<PathBuf>,
+    pub preview_seconds: f64,
+    pub preview_passes: Option<usize>,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_seconds: f64,
+    pub resume: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Render(Box<Options>),
+    Help,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            scene: None,
+            output: PathBuf::from("out.png"),
+            half: false,
+            tone_map: None,
+            exposure: None,
+            dither: false,
+            denoise: false,
+            aovs: Vec::new(),
+            width: None,
+            height: None,
+            samples_per_pixel: None,
+            max_depth: None,
+            integrator: None,
+            ao_radius: None,
+            adaptive: None,
+            min_samples: None,
+            heatmap: None,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: None,
+            sampler: None,
+            filter: None,
+            filter_radius: None,
+            tile_size: None,
+            tile_order: None,
+            progressive: false,
+            preview: None,
+            preview_seconds: 10.0,
+            preview_passes: None,
+            checkpoint: None,
+            checkpoint_seconds: 300.0,
+            resume: None,
+        }
+    }
+}
+
+fn value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("option '{}' needs a value", flag))
+}
+
+fn number<T: FromStr, I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<T, String> {
+    let text = value(args, flag)?;
+    text.parse()
+        .map_err(|_| format!("invalid value '{}' for '{}'", text, flag))
+}
+
+fn positive<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<usize, String> {
+    match number(args, flag)? {
+        0 => Err(format!("'{}' must be greater than zero", flag)),
+        n => Ok(n),
+    }
+}
+
+fn finite<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<f64, String> {
+    let x: f64 = number(args, flag)?;
+    if !x.is_finite() {
+        return Err(format!("'{}' must be a finite number", flag));
+    }
+    Ok(x)
+}
+
+// a non-negative number of seconds that fits in a Duration
+fn seconds<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<f64, String> {
+    let seconds = number(args, flag)?;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(_) => Ok(seconds),
+        Err(_) => Err(format!("'{}' must be a finite number of seconds", flag)),
+    }
+}
+
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-o" | "--output" => options.output = PathBuf::from(value(&mut args, &arg)?),
+            "--half" => options.half = true,
+            "--tonemap" => options.tone_map = Some(value(&mut args, &arg)?.parse()?),
+            "--exposure" => options.exposure = Some(finite(&mut args, &arg)?),
+            "--dither" => options.dither = true,
+            "--denoise" => options.denoise = true,
+            "--aov" => {
+                for name in value(&mut args, &arg)?.split(',') {
+                    let selected: Vec<Aov> = match name {
+                        "all" => Aov::ALL.to_vec(),
+                        _ => vec![name.parse()?],
+                    };
+                    for aov in selected {
+                        if !options.aovs.contains(&aov) {
+                            options.aovs.push(aov);
+                        }
+                    }
+                }
+            }
+            "-W" | "--width" => options.width = Some(positive(&mut args, &arg)?),
+            "-H" | "--height" => options.height = Some(positive(&mut args, &arg)?),
+            "-s" | "--spp" => options.samples_per_pixel = Some(positive(&mut args, &arg)?),
+            "-d" | "--max-depth" => options.max_depth = Some(positive(&mut args, &arg)?),
+            "--integrator" => options.integrator = Some(value(&mut args, &arg)?.parse()?),
+            "--ao-radius" => match finite(&mut args, &arg)? {
+                radius if radius > 0.0 => options.ao_radius = Some(radius),
+                _ => return Err(format!("'{}' must be greater than zero", arg)),
+            },
+            "--adaptive" => match finite(&mut args, &arg)? {
+                threshold if threshold > 0.0 => options.adaptive = Some(threshold),
+                _ => return Err(format!("'{}' must be greater than zero", arg)),
+            },
+            "--min-spp" => options.min_samples = Some(positive(&mut args, &arg)?),
+            "--heatmap" => options.heatmap = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "-t" | "--threads" => options.threads = positive(&mut args, &arg)?,
+            "--seed" => options.seed = Some(number(&mut args, &arg)?),
+            "--sampler" => options.sampler = Some(value(&mut args, &arg)?.parse()?),
+            "--filter" => options.filter = Some(value(&mut args, &arg)?.parse()?),
+            "--filter-radius" => match finite(&mut args, &arg)? {
+                radius if radius > 0.0 => options.filter_radius = Some(radius),
+                _ => return Err(format!("'{}' must be greater than zero", arg)),
+            },
+            "--tile-size" => options.tile_size = Some(positive(&mut args, &arg)?),
+            "--tile-order" => options.tile_order = Some(value(&mut args, &arg)?.parse()?),
+            "-p" | "--progressive" => options.progressive = true,
+            "--preview" => {
+                options.preview = Some(PathBuf::from(value(&mut args, &arg)?));
+                options.progressive = true;
+            }
+            "--preview-every" => options.preview_seconds = seconds(&mut args, &arg)?,
+            "--preview-passes" => options.preview_passes = Some(positive(&mut args, &arg)?),
+            "--checkpoint" => options.checkpoint = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--checkpoint-every" => options.checkpoint_seconds = seconds(&mut args, &arg)?,
+            "--resume" => options.resume = Some(PathBuf::from(value(&mut args, &arg)?)),
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option '{}'", arg))
+            }
+            _ => {
+                if options.scene.is_some() {
+                    return Err(format!("unexpected argument '{}'", arg));
+                }
+                options.scene = Some(arg);
+            }
+        }
+    }
+
+    Ok(Command::Render(Box::new(options)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{parse_args, Command, Options};
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let command = parse(&[
+            "cornell_box",
+            "-W",
+            "300",
+            "--spp",
+            "16",
+            "--threads",
+            "3",
+            "--seed",
+            "42",
+            "-o",
+            "box.png",
+        ]);
+        assert_eq!(
+            command,
+            Ok(Command::Render(Box::new(Options {
+                scene: Some("cornell_box".to_string()),
+                output: PathBuf::from("box.png"),
+                width: Some(300),
+                samples_per_pixel: Some(16),
+                threads: 3,
+                seed: Some(42),
+                ..Options::default()
+            })))
+        );
+        assert_eq!(parse(&["--width", "10", "--help"]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert_eq!(
+            parse(&["--spp", "0"]),
+            Err("'--spp' must be greater than zero".to_string())
+        );
+        assert_eq!(
+            parse(&["--width", "wide"]),
+            Err("invalid value 'wide' for '--width'".to_string())
+        );
+        assert_eq!(
+            parse(&["--output"]),
+            Err("option '--output' needs a value".to_string())
+        );
+        for &flag in ["--preview-every", "--checkpoint-every"].iter() {
+            for &value in ["-1", "inf", "NaN", "1e300"].iter() {
+                assert_eq!(
+                    parse(&[flag, value]),
+                    Err(format!("'{}' must be a finite number of seconds", flag))
+                );
+            }
+        }
+        for &flag in ["--exposure", "--adaptive", "--filter-radius", "--ao-radius"].iter() {
+            for &value in ["inf", "-inf", "NaN"].iter() {
+                assert_eq!(
+                    parse(&[flag, value]),
+                    Err(format!("'{}' must be a finite number", flag))
+                );
+            }
+        }
+        assert_eq!(
+            parse(&["--fast"]),
+            Err("unknown option '--fast'".to_string())
+        );
+        assert_eq!(
+            parse(&["a.scene", "b.scene"]),
+            Err("unexpected argument 'b.scene'".to_string())
+        );
+    }
+}
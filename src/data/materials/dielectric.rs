@@ -1,11 +1,10 @@
-use rand::random;
-
 use crate::{
-    data::Color,
+    data::{Color, Point3},
     engine::{HitRecord, Ray},
+    util::sampler::Sampler,
 };
 
-use super::Material;
+use super::{Material, ScatterRecord, Scattered};
 
 pub struct Dielectric {
     ir: f64,
@@ -16,10 +15,10 @@ impl Material for Dielectric {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
+        srec.attenuation = Color::new(1.0, 1.0, 1.0);
         let refraction_ratio = if rec.front_face {
             1.0 / self.ir
         } else {
@@ -31,16 +30,21 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction =
-            if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > random() {
-                unit_dir.reflect(&rec.normal)
-            } else {
-                unit_dir.refract(&rec.normal, refraction_ratio)
-            };
-
-        *scattered = Ray::new(rec.p, direction);
+        let direction = if cannot_refract
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.get_1d()
+        {
+            unit_dir.reflect(&rec.normal)
+        } else {
+            unit_dir.refract(&rec.normal, refraction_ratio)
+        };
+
+        srec.scattered = Scattered::Specular(Ray::new(rec.p, direction));
         true
     }
+
+    fn albedo(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
 }
 
 impl Dielectric {
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use crate::{
+    data::{
+        textures::{SharedTexture, SolidColor},
+        Color, Point3,
+    },
+    engine::{HitRecord, Ray},
+    util::sampler::Sampler,
+};
+
+use super::{Material, ScatterRecord};
+
+pub struct DiffuseLight {
+    emit: SharedTexture,
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _srec: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> bool {
+        false
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.emit.value(u, v, p)
+    }
+
+    fn albedo(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.emit.value(u, v, p).clamp(0.0, 1.0)
+    }
+}
+
+impl DiffuseLight {
+    pub fn from_texture(emit: SharedTexture) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+
+    pub fn from_color(color: Color) -> DiffuseLight {
+        DiffuseLight {
+            emit: Arc::new(SolidColor::from_color(color)),
+        }
+    }
+
+    pub fn from_rgb(r: f64, g: f64, b: f64) -> DiffuseLight {
+        DiffuseLight::from_color(Color::new(r, g, b))
+    }
+}
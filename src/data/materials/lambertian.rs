@@ -1,14 +1,16 @@
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 use crate::{
     data::{
         textures::{SharedTexture, SolidColor},
-        Color, Texture, Vec3,
+        Color, Point3, Texture,
     },
-    engine::{HitRecord, Ray},
+    engine::{CosinePdf, HitRecord, Ray},
+    util::sampler::Sampler,
 };
 
-use super::Material;
+use super::{Material, ScatterRecord, Scattered};
 
 pub struct Lambertian {
     albedo: Arc<dyn Texture + Send + Sync>,
@@ -19,17 +21,22 @@ impl Material for Lambertian {
         &self,
         _r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Vec3,
-        scattered: &mut Ray,
+        srec: &mut ScatterRecord,
+        _sampler: &mut dyn Sampler,
     ) -> bool {
-        let mut scatter_dir = rec.normal + Vec3::random_unit_vector();
-        if scatter_dir.near_zero() {
-            scatter_dir = rec.normal;
-        }
-        *scattered = Ray::new(rec.p, scatter_dir);
-        *attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        srec.attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        srec.scattered = Scattered::Pdf(Box::new(CosinePdf::new(&rec.normal)));
         true
     }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = rec.normal.dot(&scattered.dir().unit());
+        cosine.max(0.0) / PI
+    }
+
+    fn albedo(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.albedo.value(u, v, p)
+    }
 }
 
 impl Lambertian {
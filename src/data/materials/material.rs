@@ -1,16 +1,52 @@
-use std::sync::Arc;
-
 use crate::{
-    data::Color,
-    engine::{HitRecord, Ray},
+    data::{Color, Point3, Vec3},
+    engine::{HitRecord, Pdf, Ray},
+    util::sampler::Sampler,
 };
 
+// Where scattered light goes: along a single ray for mirror-like surfaces,
+// or in directions the integrator draws from a pdf.
+pub enum Scattered {
+    Specular(Ray),
+    Pdf(Box<dyn Pdf>),
+}
+
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub scattered: Scattered,
+}
+
+impl ScatterRecord {
+    pub fn empty() -> ScatterRecord {
+        ScatterRecord {
+            attenuation: Color::new(1.0, 1.0, 1.0),
+            scattered: Scattered::Specular(Ray::new(Vec3::zero(), Vec3::zero())),
+        }
+    }
+}
+
 pub trait Material {
     fn scatter(
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool;
+
+    // Density, per unit solid angle, of light arriving along `r_in` being
+    // scattered out along `scattered`. Times the attenuation this is the
+    // BRDF times the cosine. Only used for materials that scatter by a pdf.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::zero()
+    }
+
+    // surface color in [0, 1] for the albedo AOV
+    fn albedo(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::zero()
+    }
 }
@@ -1,9 +1,10 @@
 use crate::{
-    data::{Color, Vec3},
+    data::{Color, Point3},
     engine::{HitRecord, Ray},
+    util::sampler::{sample_ball, Sampler},
 };
 
-use super::Material;
+use super::{Material, ScatterRecord, Scattered};
 
 pub struct Metal {
     albedo: Color,
@@ -15,13 +16,18 @@ impl Material for Metal {
         &self,
         r_in: &Ray,
         rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
+        srec: &mut ScatterRecord,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         let reflected = r_in.dir().unit().reflect(&rec.normal);
-        *scattered = Ray::new(rec.p, reflected + self.fuzz * Vec3::random_in_unit_sphere());
-        *attenuation = self.albedo;
-        scattered.dir().dot(&rec.normal) > 0.0
+        let direction = reflected + self.fuzz * sample_ball(sampler.get_2d(), sampler.get_1d());
+        srec.attenuation = self.albedo;
+        srec.scattered = Scattered::Specular(Ray::new(rec.p, direction));
+        direction.dot(&rec.normal) > 0.0
+    }
+
+    fn albedo(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
     }
 }
 
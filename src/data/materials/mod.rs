@@ -1,9 +1,11 @@
 pub mod dielectric;
+pub mod diffuse_light;
 pub mod lambertian;
 pub mod material;
 pub mod metal;
 
 pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
 pub use lambertian::Lambertian;
-pub use material::Material;
+pub use material::{Material, ScatterRecord, Scattered};
 pub use metal::Metal;
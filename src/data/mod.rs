@@ -1,11 +1,15 @@
 pub mod materials;
+pub mod obj;
+pub mod onb;
+pub mod scene;
 pub mod textures;
 pub mod vec3;
 pub mod worlds;
 
-pub use vec3::{Color, Point3, Vec3};
+pub use vec3::{Color, Mat4, Point3, Vec3};
 
-pub use materials::{Lambertian, Material, Metal};
+pub use materials::{DiffuseLight, Lambertian, Material, Metal, ScatterRecord, Scattered};
+pub use onb::Onb;
 
 pub use textures::Texture;
 pub use worlds::marble_land;
@@ -0,0 +1,468 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::data::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::data::textures::ImageTexture;
+use crate::data::{Color, Point3, Vec3};
+use crate::engine::{HittableList, TriangleMesh};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+type SharedMaterial = Arc<dyn Material + Send + Sync>;
+
+// position, texture coordinate and normal indices of one face corner
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+#[derive(Default)]
+struct MtlDesc {
+    kd: Option<Color>,
+    ks: Option<Color>,
+    ke: Option<Color>,
+    ns: Option<f64>,
+    ni: Option<f64>,
+    dissolve: Option<f64>,
+    illum: Option<u32>,
+    map_kd: Option<PathBuf>,
+}
+
+impl MtlDesc {
+    fn build(&self) -> Result<SharedMaterial, String> {
+        let black = |c: Option<Color>| c.is_none_or(|c| c.near_zero());
+
+        if !black(self.ke) {
+            return Ok(Arc::new(DiffuseLight::from_color(self.ke.unwrap())));
+        }
+
+        let transparent = self.dissolve.is_some_and(|d| d < 1.0);
+        if transparent || matches!(self.illum, Some(4) | Some(6) | Some(7) | Some(9)) {
+            return Ok(Arc::new(Dielectric::new(self.ni.unwrap_or(1.5))));
+        }
+
+        if self.illum == Some(3) || (black(self.kd) && !black(self.ks)) {
+            let albedo = self.ks.unwrap_or_else(|| Color::new(1.0, 1.0, 1.0));
+            let fuzz = (1.0 - self.ns.unwrap_or(1000.0) / 1000.0).clamp(0.0, 1.0);
+            return Ok(Arc::new(Metal::new(
+                albedo.x(),
+                albedo.y(),
+                albedo.z(),
+                fuzz,
+            )));
+        }
+
+        if let Some(map) = &self.map_kd {
+            let texture = ImageTexture::load(&map.to_string_lossy())
+                .map_err(|e| format!("cannot load texture {}: {}", map.display(), e))?;
+            return Ok(Arc::new(Lambertian::from_texture(Arc::new(texture))));
+        }
+
+        let kd = self.kd.unwrap_or_else(|| Color::new(0.8, 0.8, 0.8));
+        Ok(Arc::new(Lambertian::from_color(kd)))
+    }
+}
+
+pub fn load_mtl(path: &Path) -> Result<HashMap<String, SharedMaterial>, ObjError> {
+    let source = read(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let parse_error = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut descs: Vec<(String, usize, MtlDesc)> = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = tokenize(line);
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            let name = args
+                .first()
+                .ok_or_else(|| parse_error(number, "newmtl without a name".to_string()))?;
+            descs.push((name.to_string(), number, MtlDesc::default()));
+            continue;
+        }
+
+        let desc = match descs.last_mut() {
+            Some((_, _, desc)) => desc,
+            None => {
+                return Err(parse_error(
+                    number,
+                    format!("'{}' before any newmtl", keyword),
+                ))
+            }
+        };
+        let result = match keyword {
+            "Kd" => parse_color(&args).map(|c| desc.kd = Some(c)),
+            "Ks" => parse_color(&args).map(|c| desc.ks = Some(c)),
+            "Ke" => parse_color(&args).map(|c| desc.ke = Some(c)),
+            "Ns" => parse_scalar(&args).map(|x| desc.ns = Some(x)),
+            "Ni" => parse_scalar(&args).map(|x| desc.ni = Some(x)),
+            "d" => parse_scalar(&args).map(|x| desc.dissolve = Some(x)),
+            "Tr" => parse_scalar(&args).map(|x| desc.dissolve = Some(1.0 - x)),
+            "illum" => parse_scalar(&args).map(|x| desc.illum = Some(x as u32)),
+            "map_Kd" => match args.last() {
+                Some(file) => {
+                    desc.map_kd = Some(dir.join(file));
+                    Ok(())
+                }
+                None => Err("map_Kd without a file name".to_string()),
+            },
+            _ => Ok(()),
+        };
+        result.map_err(|message| parse_error(number, message))?;
+    }
+
+    let mut materials = HashMap::new();
+    for (name, line, desc) in descs {
+        let material = desc.build().map_err(|message| parse_error(line, message))?;
+        materials.insert(name, material);
+    }
+    Ok(materials)
+}
+
+pub fn load_obj(path: &Path) -> Result<HittableList, ObjError> {
+    load_obj_with_default(path, Arc::new(Lambertian::from_rgb(0.73, 0.73, 0.73)))
+}
+
+pub fn load_obj_with_default(
+    path: &Path,
+    default_material: SharedMaterial,
+) -> Result<HittableList, ObjError> {
+    let source = read(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let parse_error = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut positions: Vec<Point3> = Vec::new();
+    let mut uvs: Vec<(f64, f64)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: HashMap<String, SharedMaterial> = HashMap::new();
+
+    // faces grouped by material name, in order of first use
+    let mut groups: Vec<(Option<String>, Vec<[VertexKey; 3]>)> = vec![(None, Vec::new())];
+    let mut current = 0;
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = tokenize(line);
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(&args, 3, 4).map_err(|m| parse_error(number, m))?;
+                positions.push(Point3::new(v[0], v[1], v[2]));
+            }
+            "vt" => {
+                let v = parse_floats(&args, 1, 3).map_err(|m| parse_error(number, m))?;
+                uvs.push((v[0], v.get(1).copied().unwrap_or(0.0)));
+            }
+            "vn" => {
+                let v = parse_floats(&args, 3, 3).map_err(|m| parse_error(number, m))?;
+                normals.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(
+                        number,
+                        format!("face needs at least 3 vertices, got {}", args.len()),
+                    ));
+                }
+                let corners = args
+                    .iter()
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<VertexKey>, String>>()
+                    .map_err(|m| parse_error(number, m))?;
+                for i in 1..corners.len() - 1 {
+                    groups[current]
+                        .1
+                        .push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            "usemtl" => {
+                let name = args
+                    .first()
+                    .ok_or_else(|| parse_error(number, "usemtl without a name".to_string()))?;
+                if !materials.contains_key(*name) {
+                    return Err(parse_error(number, format!("unknown material '{}'", name)));
+                }
+                current = match groups
+                    .iter()
+                    .position(|(group, _)| group.as_deref() == Some(*name))
+                {
+                    Some(index) => index,
+                    None => {
+                        groups.push((Some(name.to_string()), Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(parse_error(number, "mtllib without a file".to_string()));
+                }
+                for file in args.iter() {
+                    materials.extend(load_mtl(&dir.join(file))?);
+                }
+            }
+            // groups, smoothing and free-form statements don't affect the meshes
+            _ => {}
+        }
+    }
+
+    let mut world = HittableList::new();
+    for (name, faces) in groups {
+        if faces.is_empty() {
+            continue;
+        }
+        let material = match name {
+            Some(name) => Arc::clone(&materials[&name]),
+            None => Arc::clone(&default_material),
+        };
+        world.add(Arc::new(build_mesh(
+            &faces, &positions, &uvs, &normals, material,
+        )));
+    }
+    Ok(world)
+}
+
+fn build_mesh(
+    faces: &[[VertexKey; 3]],
+    positions: &[Point3],
+    uvs: &[(f64, f64)],
+    normals: &[Vec3],
+    material: SharedMaterial,
+) -> TriangleMesh {
+    let has_uvs = faces.iter().flatten().all(|key| key.1.is_some());
+    let has_normals = faces.iter().flatten().all(|key| key.2.is_some());
+
+    let mut remap: HashMap<VertexKey, usize> = HashMap::new();
+    let mut mesh_positions = Vec::new();
+    let mut mesh_uvs = Vec::new();
+    let mut mesh_normals = Vec::new();
+    let mut indices = Vec::with_capacity(faces.len());
+
+    for face in faces {
+        let mut triangle = [0; 3];
+        for (corner, key) in triangle.iter_mut().zip(face.iter()) {
+            *corner = *remap.entry(*key).or_insert_with(|| {
+                mesh_positions.push(positions[key.0]);
+                if has_uvs {
+                    mesh_uvs.push(uvs[key.1.unwrap()]);
+                }
+                if has_normals {
+                    mesh_normals.push(normals[key.2.unwrap()].unit());
+                }
+                mesh_positions.len() - 1
+            });
+        }
+        indices.push(triangle);
+    }
+
+    TriangleMesh::new(mesh_positions, mesh_normals, mesh_uvs, indices, material)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+fn tokenize(line: &str) -> std::str::SplitWhitespace<'_> {
+    let line = match line.find('#') {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+    line.split_whitespace()
+}
+
+fn parse_floats(args: &[&str], min: usize, max: usize) -> Result<Vec<f64>, String> {
+    if args.len() < min || args.len() > max {
+        return Err(if min == max {
+            format!("expected {} numbers, got {}", min, args.len())
+        } else {
+            format!("expected {} to {} numbers, got {}", min, max, args.len())
+        });
+    }
+    args.iter()
+        .map(|arg| match arg.parse::<f64>() {
+            Ok(x) if x.is_finite() => Ok(x),
+            _ => Err(format!("invalid number '{}'", arg)),
+        })
+        .collect()
+}
+
+fn parse_scalar(args: &[&str]) -> Result<f64, String> {
+    parse_floats(args, 1, 1).map(|v| v[0])
+}
+
+fn parse_color(args: &[&str]) -> Result<Color, String> {
+    parse_floats(args, 3, 3).map(|v| Color::new(v[0], v[1], v[2]))
+}
+
+fn parse_index(token: &str, count: usize, what: &str) -> Result<usize, String> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("invalid {} index '{}'", what, token))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!(
+            "{} index {} out of range ({} defined)",
+            what, index, count
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_corner(
+    corner: &str,
+    n_positions: usize,
+    n_uvs: usize,
+    n_normals: usize,
+) -> Result<VertexKey, String> {
+    let mut parts = corner.split('/');
+    let position = parse_index(parts.next().unwrap_or(""), n_positions, "vertex")?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(token) => Some(parse_index(token, n_uvs, "texture")?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(token) => Some(parse_index(token, n_normals, "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("malformed face vertex '{}'", corner));
+    }
+    Ok((position, uv, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::data::Vec3;
+    use crate::engine::{HitRecord, Hittable, Ray};
+
+    use super::{load_obj, ObjError};
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("rust_tracer_obj_tests");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn quad_with_material() {
+        write_temp("quad.mtl", "newmtl light\nKe 4 4 4\n");
+        let path = write_temp(
+            "quad.obj",
+            "mtllib quad.mtl\n\
+             v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\n\
+             vn 0 0 1\n\
+             usemtl light\n\
+             f 1//1 2//1 3//1 4//1 # quad\n",
+        );
+        let world = load_obj(&path).unwrap();
+
+        let mut rec = HitRecord::empty();
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(world.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.p, Vec3::new(0.5, 0.5, 0.0));
+        assert_eq!(
+            rec.mat_ptr.emitted(rec.u, rec.v, &rec.p),
+            Vec3::new(4.0, 4.0, 4.0)
+        );
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let path = write_temp("broken.obj", "v 0 0 0\nv 1 0 0\nv 0 1 zero\n");
+        match load_obj(&path) {
+            Err(ObjError::Parse { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(message, "invalid number 'zero'");
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        let path = write_temp("out_of_range.obj", "v 0 0 0\nv 1 0 0\nf 1 2 3\n");
+        match load_obj(&path) {
+            Err(ObjError::Parse { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(message, "vertex index 3 out of range (2 defined)");
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        // numbers that are not finite would break materials and the BVH
+        let path = write_temp("nan.obj", "v 0 0 0\nv 1 0 0\nv 0 1 -nan\n");
+        match load_obj(&path) {
+            Err(ObjError::Parse { line, message, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(message, "invalid number '-nan'");
+            }
+            _ => panic!("expected a parse error"),
+        }
+        write_temp("nan.mtl", "newmtl steel\nKs 1 1 1\nNs nan\n");
+        let path = write_temp("nan_material.obj", "mtllib nan.mtl\nv 0 0 0\n");
+        match load_obj(&path) {
+            Err(ObjError::Parse {
+                path,
+                line,
+                message,
+            }) => {
+                assert_eq!((path.file_name().unwrap(), line), ("nan.mtl".as_ref(), 3));
+                assert_eq!(message, "invalid number 'nan'");
+            }
+            _ => panic!("expected a parse error"),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use super::SceneError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    LBrace,
+    RBrace,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl TokenKind {
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Ident(name) => format!("'{}'", name),
+            TokenKind::Number(x) => format!("number {}", x),
+            TokenKind::Str(s) => format!("string \"{}\"", s),
+            TokenKind::LBrace => "'{'".to_string(),
+            TokenKind::RBrace => "'}'".to_string(),
+            TokenKind::Eof => "end of file".to_string(),
+        }
+    }
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Token>, SceneError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let (mut line, mut column) = (1, 1);
+
+    while let Some(&c) = chars.peek() {
+        let (start_line, start_column) = (line, column);
+        let token = |kind| Token {
+            kind,
+            line: start_line,
+            column: start_column,
+        };
+
+        if c == '\n' {
+            chars.next();
+            line += 1;
+            column = 1;
+        } else if c.is_whitespace() {
+            chars.next();
+            column += 1;
+        } else if c == '#' {
+            while let Some(&c) = chars.peek() {
+                if c == '\n' {
+                    break;
+                }
+                chars.next();
+            }
+        } else if c == '{' || c == '}' {
+            chars.next();
+            column += 1;
+            tokens.push(token(if c == '{' {
+                TokenKind::LBrace
+            } else {
+                TokenKind::RBrace
+            }));
+        } else if c == '"' {
+            chars.next();
+            column += 1;
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => {
+                        column += 1;
+                        break;
+                    }
+                    Some('\n') | None => {
+                        return Err(SceneError::new(
+                            start_line,
+                            start_column,
+                            "unterminated string",
+                        ))
+                    }
+                    Some(c) => {
+                        column += 1;
+                        value.push(c);
+                    }
+                }
+            }
+            tokens.push(token(TokenKind::Str(value)));
+        } else if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' {
+            let mut text = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.') {
+                    break;
+                }
+                text.push(c);
+                chars.next();
+                column += 1;
+            }
+            let value = text.parse::<f64>().map_err(|_| {
+                SceneError::new(
+                    start_line,
+                    start_column,
+                    &format!("invalid number '{}'", text),
+                )
+            })?;
+            tokens.push(token(TokenKind::Number(value)));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut text = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                text.push(c);
+                chars.next();
+                column += 1;
+            }
+            tokens.push(token(TokenKind::Ident(text)));
+        } else {
+            return Err(SceneError::new(
+                line,
+                column,
+                &format!("unexpected character '{}'", c),
+            ));
+        }
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        line,
+        column,
+    });
+    Ok(tokens)
+}
@@ -0,0 +1,98 @@
+pub mod lexer;
+pub mod parser;
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::engine::{Camera, CameraConfig, Hittable, HittableList};
+use crate::render::RenderSettings;
+
+pub use parser::{load_scene, parse_scene};
+
+pub struct Scene {
+    pub world: Arc<dyn Hittable + Send + Sync>,
+    // emitters in the world that are sampled directly
+    pub lights: Arc<HittableList>,
+    pub camera: CameraConfig,
+    pub settings: RenderSettings,
+}
+
+impl Scene {
+    pub fn new(world: Arc<dyn Hittable + Send + Sync>) -> Scene {
+        Scene {
+            world,
+            lights: Arc::new(HittableList::new()),
+            camera: CameraConfig::default(),
+            settings: RenderSettings::default(),
+        }
+    }
+
+    pub fn build_camera(&self) -> Camera {
+        self.camera.build(self.settings.aspect_ratio())
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Parse {
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl SceneError {
+    pub fn new(line: usize, column: usize, message: &str) -> SceneError {
+        SceneError::Parse {
+            path: None,
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    fn with_path(self, file: PathBuf) -> SceneError {
+        match self {
+            SceneError::Parse {
+                line,
+                column,
+                message,
+                ..
+            } => SceneError::Parse {
+                path: Some(file),
+                line,
+                column,
+                message,
+            },
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            SceneError::Parse {
+                path: Some(path),
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            SceneError::Parse {
+                path: None,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}: {}", line, column, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
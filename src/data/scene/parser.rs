@@ -0,0 +1,837 @@
+// Scene files are a sequence of statements, each a keyword followed by a
+// block of `property value...` pairs:
+//
+//   render { width 800 height 450 samples 50 max_depth 50
+//            integrator path|direct|ao|normals|uv|distance|bvh_cost|material_id
+//            ao_radius 1 background sky
+//            tonemap clamp|reinhard|aces exposure 0 adaptive 0.05 min_samples 16
+//            sampler random|stratified|halton|sobol
+//            filter box|tent|gaussian|mitchell|lanczos filter_radius 2 }
+//   camera { look_from 13 2 3 look_at 0 0 0 up 0 1 0 vfov 20 aperture 0 focus_dist 10 }
+//   texture NAME solid|checker|image|perlin { ... }
+//   material NAME lambertian|metal|dielectric|light { ... }
+//   object sphere|xy_rect|xz_rect|yz_rect|box|triangle|mesh { ... }
+//   define NAME <object type> { ... }
+//   instance NAME { translate x y z rotate_y deg scale s }
+//
+// Objects, definitions and instances accept translate, rotate_x, rotate_y,
+// rotate_z and scale, applied in the order they are written. Paths are
+// relative to the scene file and `#` starts a comment. Spheres and
+// rectangles with a light material and no transform are also sampled
+// directly as lights.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::data::materials::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::data::obj::load_obj_with_default;
+use crate::data::textures::{
+    CheckerTexture, ImageTexture, PerlinTexture, SharedTexture, SolidColor,
+};
+use crate::data::{Color, Mat4, Vec3};
+use crate::engine::{
+    Background, BoxShape, CameraConfig, Hittable, HittableList, Sphere, Transform, Triangle,
+    XYRect, XZRect, YZRect,
+};
+use crate::render::{
+    AdaptiveSampling, Filter, FilterKind, IntegratorKind, PostProcess, RenderSettings,
+};
+use crate::util::rng::seeded_rng;
+use crate::util::sampler::SamplerKind;
+
+use super::lexer::{tokenize, Token, TokenKind};
+use super::{Scene, SceneError};
+
+type SharedMaterial = Arc<dyn Material + Send + Sync>;
+type SharedHittable = Arc<dyn Hittable + Send + Sync>;
+
+pub fn load_scene(path: &Path) -> Result<Scene, SceneError> {
+    let source = fs::read_to_string(path).map_err(|error| SceneError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_scene(&source, base_dir).map_err(|e| e.with_path(path.to_path_buf()))
+}
+
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        pos: 0,
+        base_dir: base_dir.to_path_buf(),
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        definitions: HashMap::new(),
+        world: HittableList::new(),
+        camera: CameraConfig::default(),
+        background: Background::Sky,
+        width: None,
+        height: None,
+        aspect_ratio: None,
+        samples_per_pixel: None,
+        max_depth: None,
+        integrator: None,
+        ao_radius: None,
+        adaptive: None,
+        min_samples: None,
+        lights: HittableList::new(),
+        light_materials: HashSet::new(),
+        sampler: None,
+        filter: None,
+        filter_radius: None,
+        post: PostProcess::default(),
+    };
+    parser.parse()?;
+    // checked at the end of every render block
+    let (width, height) = parser.size().unwrap();
+
+    let mut scene = Scene::new(parser.world.build());
+    scene.lights = Arc::new(parser.lights);
+    let settings = &mut scene.settings;
+    scene.camera = parser.camera;
+    settings.background = parser.background;
+    settings.width = width;
+    settings.height = height;
+    settings.samples_per_pixel = parser
+        .samples_per_pixel
+        .unwrap_or(settings.samples_per_pixel);
+    settings.max_depth = parser.max_depth.unwrap_or(settings.max_depth);
+    settings.integrator = parser.integrator.unwrap_or(settings.integrator);
+    if let (IntegratorKind::AmbientOcclusion { radius }, Some(ao_radius)) =
+        (&mut settings.integrator, parser.ao_radius)
+    {
+        *radius = ao_radius;
+    }
+    let min_samples = parser
+        .min_samples
+        .unwrap_or(AdaptiveSampling::default().min_samples);
+    settings.adaptive = parser.adaptive.map(|threshold| AdaptiveSampling {
+        threshold,
+        min_samples,
+    });
+    settings.sampler = parser.sampler.unwrap_or(settings.sampler);
+    let kind = parser.filter.unwrap_or(settings.filter.kind);
+    settings.filter = Filter {
+        kind,
+        radius: parser
+            .filter_radius
+            .unwrap_or_else(|| kind.default_radius()),
+    };
+    settings.post = parser.post;
+    Ok(scene)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    base_dir: PathBuf,
+    textures: HashMap<String, SharedTexture>,
+    materials: HashMap<String, SharedMaterial>,
+    definitions: HashMap<String, SharedHittable>,
+    world: HittableList,
+    lights: HittableList,
+    light_materials: HashSet<String>,
+    camera: CameraConfig,
+    background: Background,
+    width: Option<usize>,
+    height: Option<usize>,
+    aspect_ratio: Option<f64>,
+    samples_per_pixel: Option<usize>,
+    max_depth: Option<usize>,
+    integrator: Option<IntegratorKind>,
+    ao_radius: Option<f64>,
+    adaptive: Option<f64>,
+    min_samples: Option<usize>,
+    sampler: Option<SamplerKind>,
+    filter: Option<FilterKind>,
+    filter_radius: Option<f64>,
+    post: PostProcess,
+}
+
+fn error(token: &Token, message: &str) -> SceneError {
+    SceneError::new(token.line, token.column, message)
+}
+
+fn unexpected(token: &Token, expected: &str) -> SceneError {
+    error(
+        token,
+        &format!("expected {}, found {}", expected, token.kind.describe()),
+    )
+}
+
+fn required<T>(value: Option<T>, token: &Token, kind: &str, name: &str) -> Result<T, SceneError> {
+    value.ok_or_else(|| error(token, &format!("{} needs '{}'", kind, name)))
+}
+
+fn unique<T>(
+    taken: &HashMap<String, T>,
+    (name, token): &(String, Token),
+    kind: &str,
+) -> Result<(), SceneError> {
+    if taken.contains_key(name) {
+        return Err(error(
+            token,
+            &format!("{} '{}' is already defined", kind, name),
+        ));
+    }
+    Ok(())
+}
+
+impl Parser {
+    fn parse(&mut self) -> Result<(), SceneError> {
+        loop {
+            let token = self.next();
+            let keyword = match &token.kind {
+                TokenKind::Eof => return Ok(()),
+                TokenKind::Ident(keyword) => keyword.clone(),
+                _ => return Err(unexpected(&token, "a statement")),
+            };
+            match keyword.as_str() {
+                "render" => self.render(&token)?,
+                "camera" => self.camera()?,
+                "texture" => {
+                    let name = self.ident()?;
+                    unique(&self.textures, &name, "texture")?;
+                    let texture = self.texture()?;
+                    self.textures.insert(name.0, texture);
+                }
+                "material" => {
+                    let name = self.ident()?;
+                    unique(&self.materials, &name, "material")?;
+                    let (material, is_light) = self.material()?;
+                    if is_light {
+                        self.light_materials.insert(name.0.clone());
+                    }
+                    self.materials.insert(name.0, material);
+                }
+                "object" => {
+                    let (object, is_light) = self.object()?;
+                    if is_light {
+                        self.lights.add(Arc::clone(&object));
+                    }
+                    self.world.add(object);
+                }
+                "define" => {
+                    let name = self.ident()?;
+                    unique(&self.definitions, &name, "definition")?;
+                    let (object, _) = self.object()?;
+                    self.definitions.insert(name.0, object);
+                }
+                "instance" => {
+                    let instance = self.instance()?;
+                    self.world.add(instance);
+                }
+                _ => return Err(error(&token, &format!("unknown statement '{}'", keyword))),
+            }
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn ident(&mut self) -> Result<(String, Token), SceneError> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Ident(name) => Ok((name.clone(), token.clone())),
+            _ => Err(unexpected(&token, "a name")),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, SceneError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Number(x) if x.is_finite() => Ok(x),
+            TokenKind::Number(x) => Err(error(
+                &token,
+                &format!("expected a finite number, found {}", x),
+            )),
+            _ => Err(unexpected(&token, "a number")),
+        }
+    }
+
+    fn count(&mut self) -> Result<usize, SceneError> {
+        let token = self.peek().clone();
+        let x = self.number()?;
+        if x < 1.0 || x.fract() != 0.0 || x > u32::MAX as f64 {
+            return Err(error(
+                &token,
+                &format!(
+                    "expected a positive integer up to {}, found {}",
+                    u32::MAX,
+                    x
+                ),
+            ));
+        }
+        Ok(x as usize)
+    }
+
+    // the image size the render settings give, if it has at most u32::MAX
+    // pixels
+    fn size(&self) -> Option<(usize, usize)> {
+        let defaults = RenderSettings::default();
+        let width = self.width.unwrap_or(defaults.width);
+        let height = match self.height {
+            Some(height) => height,
+            None => {
+                let aspect_ratio = self.aspect_ratio.unwrap_or_else(|| defaults.aspect_ratio());
+                let height = (width as f64 / aspect_ratio).max(1.0);
+                if height > u32::MAX as f64 {
+                    return None;
+                }
+                height as usize
+            }
+        };
+        match width.checked_mul(height) {
+            Some(pixels) if pixels <= u32::MAX as usize => Some((width, height)),
+            _ => None,
+        }
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, SceneError> {
+        Ok(Vec3::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    fn path(&mut self) -> Result<PathBuf, SceneError> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Str(path) => Ok(self.base_dir.join(path)),
+            _ => Err(unexpected(&token, "a quoted file name")),
+        }
+    }
+
+    // the material and whether it is a light
+    fn material_ref(&mut self) -> Result<(SharedMaterial, bool), SceneError> {
+        let (name, token) = self.ident()?;
+        let material = self
+            .materials
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| error(&token, &format!("unknown material '{}'", name)))?;
+        Ok((material, self.light_materials.contains(&name)))
+    }
+
+    // either an inline `r g b` color or the name of a texture
+    fn texture_ref(&mut self) -> Result<SharedTexture, SceneError> {
+        if let TokenKind::Number(_) = self.peek().kind {
+            return Ok(Arc::new(SolidColor::from_color(self.vec3()?)));
+        }
+        let (name, token) = self.ident()?;
+        self.textures
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| error(&token, &format!("unknown texture '{}'", name)))
+    }
+
+    fn block<F>(&mut self, kind: &str, mut property: F) -> Result<(), SceneError>
+    where
+        F: FnMut(&mut Parser, &str) -> Result<bool, SceneError>,
+    {
+        let token = self.next();
+        if token.kind != TokenKind::LBrace {
+            return Err(unexpected(&token, "'{'"));
+        }
+        loop {
+            let token = self.next();
+            match &token.kind {
+                TokenKind::RBrace => return Ok(()),
+                TokenKind::Ident(key) => {
+                    if !property(self, key)? {
+                        return Err(error(
+                            &token,
+                            &format!("unknown {} property '{}'", kind, key),
+                        ));
+                    }
+                }
+                _ => return Err(unexpected(&token, "a property name or '}'")),
+            }
+        }
+    }
+
+    fn render(&mut self, token: &Token) -> Result<(), SceneError> {
+        self.block("render", |p, key| {
+            match key {
+                "width" => p.width = Some(p.count()?),
+                "height" => p.height = Some(p.count()?),
+                "aspect" => {
+                    let token = p.peek().clone();
+                    let aspect_ratio = p.number()?;
+                    if aspect_ratio <= 0.0 {
+                        return Err(error(&token, "the aspect ratio must be positive"));
+                    }
+                    p.aspect_ratio = Some(aspect_ratio);
+                }
+                "samples" => p.samples_per_pixel = Some(p.count()?),
+                "max_depth" => p.max_depth = Some(p.count()?),
+                "integrator" => {
+                    let (name, token) = p.ident()?;
+                    p.integrator = Some(name.parse().map_err(|e: String| error(&token, &e))?);
+                }
+                "ao_radius" => {
+                    let token = p.peek().clone();
+                    let radius = p.number()?;
+                    if radius <= 0.0 {
+                        return Err(error(
+                            &token,
+                            "the ambient occlusion radius must be positive",
+                        ));
+                    }
+                    p.ao_radius = Some(radius);
+                }
+                "adaptive" => {
+                    let token = p.peek().clone();
+                    let threshold = p.number()?;
+                    if threshold <= 0.0 {
+                        return Err(error(&token, "the adaptive threshold must be positive"));
+                    }
+                    p.adaptive = Some(threshold);
+                }
+                "min_samples" => p.min_samples = Some(p.count()?),
+                "sampler" => {
+                    let (name, token) = p.ident()?;
+                    p.sampler = Some(name.parse().map_err(|e: String| error(&token, &e))?);
+                }
+                "filter" => {
+                    let (name, token) = p.ident()?;
+                    p.filter = Some(name.parse().map_err(|e: String| error(&token, &e))?);
+                }
+                "filter_radius" => {
+                    let token = p.peek().clone();
+                    let radius = p.number()?;
+                    if radius <= 0.0 {
+                        return Err(error(&token, "the filter radius must be positive"));
+                    }
+                    p.filter_radius = Some(radius);
+                }
+                "exposure" => p.post.exposure = p.number()?,
+                "tonemap" => {
+                    let (name, token) = p.ident()?;
+                    p.post.tone_map = name.parse().map_err(|e: String| error(&token, &e))?;
+                }
+                "background" => {
+                    p.background = match &p.peek().kind {
+                        TokenKind::Ident(name) if name == "sky" => {
+                            p.next();
+                            Background::Sky
+                        }
+                        _ => Background::Solid(p.vec3()?),
+                    }
+                }
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+        if self.size().is_none() {
+            return Err(error(token, "the image would have too many pixels"));
+        }
+        Ok(())
+    }
+
+    fn camera(&mut self) -> Result<(), SceneError> {
+        self.block("camera", |p, key| {
+            match key {
+                "look_from" => p.camera.look_from = p.vec3()?,
+                "look_at" => p.camera.look_at = p.vec3()?,
+                "up" => p.camera.up = p.vec3()?,
+                "vfov" => p.camera.vfov = p.number()?,
+                "aperture" => p.camera.aperture = p.number()?,
+                "focus_dist" => p.camera.focus_dist = p.number()?,
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })
+    }
+
+    fn texture(&mut self) -> Result<SharedTexture, SceneError> {
+        let (kind, token) = self.ident()?;
+        match kind.as_str() {
+            "solid" => {
+                let mut color = None;
+                self.block("solid", |p, key| match key {
+                    "color" => {
+                        color = Some(p.vec3()?);
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                })?;
+                let color = required(color, &token, "solid", "color")?;
+                Ok(Arc::new(SolidColor::from_color(color)))
+            }
+            "checker" => {
+                let (mut even, mut odd) = (None, None);
+                self.block("checker", |p, key| {
+                    match key {
+                        "even" => even = Some(p.texture_ref()?),
+                        "odd" => odd = Some(p.texture_ref()?),
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                Ok(Arc::new(CheckerTexture::new(
+                    required(even, &token, "checker", "even")?,
+                    required(odd, &token, "checker", "odd")?,
+                )))
+            }
+            "image" => {
+                let mut file = None;
+                self.block("image", |p, key| match key {
+                    "file" => {
+                        file = Some(p.path()?);
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                })?;
+                let file = required(file, &token, "image", "file")?;
+                let image = ImageTexture::load(&file.to_string_lossy()).map_err(|e| {
+                    error(
+                        &token,
+                        &format!("cannot load image {}: {}", file.display(), e),
+                    )
+                })?;
+                Ok(Arc::new(image))
+            }
+            "perlin" => {
+                let mut scale = 1.0;
+                let mut seed = 0;
+                self.block("perlin", |p, key| match key {
+                    "scale" => {
+                        scale = p.number()?;
+                        Ok(true)
+                    }
+                    "seed" => {
+                        seed = p.number()? as u64;
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                })?;
+                Ok(Arc::new(PerlinTexture::new(scale, &mut seeded_rng(seed))))
+            }
+            _ => Err(error(&token, &format!("unknown texture type '{}'", kind))),
+        }
+    }
+
+    // the material and whether it is a light
+    fn material(&mut self) -> Result<(SharedMaterial, bool), SceneError> {
+        let (kind, token) = self.ident()?;
+        let material: SharedMaterial = match kind.as_str() {
+            "lambertian" | "light" => {
+                let mut texture = None;
+                self.block(&kind, |p, key| match key {
+                    "color" | "texture" => {
+                        texture = Some(p.texture_ref()?);
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                })?;
+                let texture = required(texture, &token, &kind, "color")?;
+                if kind == "light" {
+                    Arc::new(DiffuseLight::from_texture(texture))
+                } else {
+                    Arc::new(Lambertian::from_texture(texture))
+                }
+            }
+            "metal" => {
+                let (mut color, mut fuzz) = (None, 0.0);
+                let mut fuzz_token = token.clone();
+                self.block("metal", |p, key| {
+                    match key {
+                        "color" => color = Some(p.vec3()?),
+                        "fuzz" => {
+                            fuzz_token = p.peek().clone();
+                            fuzz = p.number()?;
+                        }
+                        _ => return Ok(false),
+                    }
+                    Ok(true)
+                })?;
+                let color: Color = required(color, &token, "metal", "color")?;
+                if !(0.0..=1.0).contains(&fuzz) {
+                    return Err(error(&fuzz_token, "fuzz must be between 0 and 1"));
+                }
+                Arc::new(Metal::new(color.x(), color.y(), color.z(), fuzz))
+            }
+            "dielectric" => {
+                let mut ir = None;
+                self.block("dielectric", |p, key| match key {
+                    "ir" => {
+                        ir = Some(p.number()?);
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                })?;
+                let ir = required(ir, &token, "dielectric", "ir")?;
+                Arc::new(Dielectric::new(ir))
+            }
+            _ => return Err(error(&token, &format!("unknown material type '{}'", kind))),
+        };
+        Ok((material, kind == "light"))
+    }
+
+    fn transform(&mut self, key: &str, matrix: &mut Option<Mat4>) -> Result<bool, SceneError> {
+        let step = match key {
+            "translate" => Mat4::translation(&self.vec3()?),
+            "rotate_x" => Mat4::rotation(&Vec3::new(1.0, 0.0, 0.0), self.number()?),
+            "rotate_y" => Mat4::rotation(&Vec3::new(0.0, 1.0, 0.0), self.number()?),
+            "rotate_z" => Mat4::rotation(&Vec3::new(0.0, 0.0, 1.0), self.number()?),
+            "scale" => {
+                let x = self.number()?;
+                let factors = match self.peek().kind {
+                    TokenKind::Number(_) => Vec3::new(x, self.number()?, self.number()?),
+                    _ => Vec3::new(x, x, x),
+                };
+                Mat4::scaling(&factors)
+            }
+            _ => return Ok(false),
+        };
+        *matrix = Some(step * matrix.unwrap_or_else(Mat4::identity));
+        Ok(true)
+    }
+
+    fn transformed(
+        object: SharedHittable,
+        matrix: Option<Mat4>,
+        token: &Token,
+    ) -> Result<SharedHittable, SceneError> {
+        match matrix {
+            None => Ok(object),
+            Some(matrix) => match Transform::new(object, matrix) {
+                Some(transform) => Ok(Arc::new(transform)),
+                None => Err(error(token, "transform is not invertible")),
+            },
+        }
+    }
+
+    // the object and whether it can be sampled as a light
+    fn object(&mut self) -> Result<(SharedHittable, bool), SceneError> {
+        let (kind, token) = self.ident()?;
+        let mut matrix = None;
+        let mut material = None;
+        let mut is_light = false;
+        let mut points: HashMap<&'static str, Vec3> = HashMap::new();
+        let mut scalars: HashMap<&'static str, f64> = HashMap::new();
+        let mut file = None;
+
+        let point_keys: &[&'static str] = match kind.as_str() {
+            "sphere" => &["center"],
+            "box" => &["min", "max"],
+            "triangle" => &["a", "b", "c"],
+            _ => &[],
+        };
+        let scalar_keys: &[&'static str] = match kind.as_str() {
+            "sphere" => &["radius"],
+            "xy_rect" => &["x0", "x1", "y0", "y1", "k"],
+            "xz_rect" => &["x0", "x1", "z0", "z1", "k"],
+            "yz_rect" => &["y0", "y1", "z0", "z1", "k"],
+            "box" | "triangle" | "mesh" => &[],
+            _ => return Err(error(&token, &format!("unknown object type '{}'", kind))),
+        };
+
+        self.block(&kind, |p, key| {
+            if key == "material" {
+                let (shared, light) = p.material_ref()?;
+                material = Some(shared);
+                is_light = light;
+            } else if key == "file" && kind == "mesh" {
+                file = Some(p.path()?);
+            } else if let Some(&key) = point_keys.iter().find(|&&k| k == key) {
+                points.insert(key, p.vec3()?);
+            } else if let Some(&key) = scalar_keys.iter().find(|&&k| k == key) {
+                scalars.insert(key, p.number()?);
+            } else {
+                return p.transform(key, &mut matrix);
+            }
+            Ok(true)
+        })?;
+
+        for key in point_keys {
+            required(points.get(key), &token, &kind, key)?;
+        }
+        for key in scalar_keys {
+            required(scalars.get(key), &token, &kind, key)?;
+        }
+        let s = |key| scalars[key];
+
+        let object: SharedHittable = if kind == "mesh" {
+            let file = required(file, &token, "mesh", "file")?;
+            let default_material =
+                material.unwrap_or_else(|| Arc::new(Lambertian::from_rgb(0.73, 0.73, 0.73)));
+            let mesh = load_obj_with_default(&file, default_material)
+                .map_err(|e| error(&token, &e.to_string()))?;
+            Arc::new(mesh)
+        } else {
+            let material = required(material, &token, &kind, "material")?;
+            match kind.as_str() {
+                "sphere" => Arc::new(Sphere::new(points["center"], s("radius"), material)),
+                "xy_rect" => Arc::new(XYRect::new(
+                    s("x0"),
+                    s("x1"),
+                    s("y0"),
+                    s("y1"),
+                    s("k"),
+                    material,
+                )),
+                "xz_rect" => Arc::new(XZRect::new(
+                    s("x0"),
+                    s("x1"),
+                    s("z0"),
+                    s("z1"),
+                    s("k"),
+                    material,
+                )),
+                "yz_rect" => Arc::new(YZRect::new(
+                    s("y0"),
+                    s("y1"),
+                    s("z0"),
+                    s("z1"),
+                    s("k"),
+                    material,
+                )),
+                "box" => Arc::new(BoxShape::new(points["min"], points["max"], material)),
+                _ => Arc::new(Triangle::new(
+                    [points["a"], points["b"], points["c"]],
+                    material,
+                )),
+            }
+        };
+        let sampled = matches!(kind.as_str(), "sphere" | "xy_rect" | "xz_rect" | "yz_rect");
+        let is_light = is_light && sampled && matrix.is_none();
+        Ok((Parser::transformed(object, matrix, &token)?, is_light))
+    }
+
+    fn instance(&mut self) -> Result<SharedHittable, SceneError> {
+        let (name, token) = self.ident()?;
+        let object = match self.definitions.get(&name) {
+            Some(object) => Arc::clone(object),
+            None => return Err(error(&token, &format!("unknown definition '{}'", name))),
+        };
+        let mut matrix = None;
+        self.block("instance", |p, key| p.transform(key, &mut matrix))?;
+        Parser::transformed(object, matrix, &token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::data::Vec3;
+    use crate::engine::{HitRecord, Ray};
+    use crate::render::{AdaptiveSampling, Filter, FilterKind, IntegratorKind};
+
+    use super::{parse_scene, SceneError};
+
+    #[test]
+    fn parses_scene() {
+        let scene = parse_scene(
+            r#"
+            # a small lit room
+            render { width 200 aspect 2 samples 8 max_depth 5 background 0 0 0 adaptive 0.1
+                     filter gaussian integrator direct }
+            camera { look_from 0 0 10 look_at 0 0 0 vfov 40 }
+            texture tiles checker { even 0 0 0 odd 1 1 1 }
+            material floor lambertian { texture tiles }
+            material lamp light { color 4 4 4 }
+            object xz_rect { x0 -5 x1 5 z0 -5 z1 5 k -1 material floor }
+            object xy_rect { x0 -1 x1 1 y0 4 y1 5 k -5 material lamp }
+            define ball sphere { center 0 0 0 radius 1 material lamp }
+            instance ball { scale 2 1 1 translate 0 3 0 }
+            "#,
+            Path::new(""),
+        )
+        .unwrap();
+
+        assert_eq!((scene.settings.width, scene.settings.height), (200, 100));
+        assert_eq!(scene.settings.samples_per_pixel, 8);
+        assert_eq!(
+            scene.settings.adaptive,
+            Some(AdaptiveSampling {
+                threshold: 0.1,
+                min_samples: 16
+            })
+        );
+        assert_eq!(scene.settings.filter, Filter::new(FilterKind::Gaussian));
+        assert_eq!(scene.settings.integrator, IntegratorKind::Direct);
+        assert_eq!(scene.camera.look_from, Vec3::new(0.0, 0.0, 10.0));
+        // the lamp rectangle, but not the transformed lamp instance
+        assert_eq!(scene.lights.len(), 1);
+
+        let mut rec = HitRecord::empty();
+        let ray = Ray::new(Vec3::new(-10.0, 3.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(scene.world.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.p, Vec3::new(-2.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn reports_position() {
+        let result = parse_scene(
+            "material red lambertian { color 1 0 0 }\nobject sphere {\n  center 0 0 0\n  radius 1 material blue\n}\n",
+            Path::new(""),
+        );
+        match result {
+            Err(SceneError::Parse {
+                line,
+                column,
+                message,
+                ..
+            }) => {
+                assert_eq!((line, column), (4, 21));
+                assert_eq!(message, "unknown material 'blue'");
+            }
+            _ => panic!("expected a parse error"),
+        }
+
+        match parse_scene("object sphere { center 0 0 radius 1 }", Path::new("")) {
+            Err(SceneError::Parse { message, .. }) => {
+                assert_eq!(message, "expected a number, found 'radius'")
+            }
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn rejects_sizes_and_numbers_out_of_range() {
+        for &(source, expected) in [
+            (
+                "render { width 20 aspect 0 }",
+                "the aspect ratio must be positive",
+            ),
+            (
+                "render { width 1e30 }",
+                "expected a positive integer up to 4294967295, found 1000000000000000000000000000000",
+            ),
+            (
+                "render { width 100000 height 100000 }",
+                "the image would have too many pixels",
+            ),
+            (
+                "render { width 100000 aspect 1e-9 }",
+                "the image would have too many pixels",
+            ),
+            (
+                "object sphere { center 0 0 0 radius 1 rotate_y -nan }",
+                "expected a finite number, found NaN",
+            ),
+            (
+                "object sphere { center 0 0 0 radius 1 rotate_y -inf }",
+                "expected a finite number, found -inf",
+            ),
+        ]
+        .iter()
+        {
+            match parse_scene(source, Path::new("")) {
+                Err(SceneError::Parse { message, .. }) => assert_eq!(message, expected),
+                _ => panic!("expected a parse error for {}", source),
+            }
+        }
+    }
+}
@@ -1,6 +1,3 @@
-use lodepng;
-use rgb;
-
 use crate::data::{Color, Vec3};
 
 use super::Texture;
@@ -13,17 +10,21 @@ pub struct ImageTexture {
 
 impl ImageTexture {
     pub fn new(filename: &str) -> ImageTexture {
-        let image = lodepng::decode32_file(filename).unwrap();
-        ImageTexture {
+        ImageTexture::load(filename).unwrap()
+    }
+
+    pub fn load(filename: &str) -> Result<ImageTexture, lodepng::Error> {
+        let image = lodepng::decode32_file(filename)?;
+        Ok(ImageTexture {
             data: image.buffer,
             width: image.width,
             height: image.height,
-        }
+        })
     }
 }
 
 impl Texture for ImageTexture {
-    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+    fn value(&self, u: f64, v: f64, _p: &Vec3) -> Color {
         let u = u.clamp(0.0, 1.0);
         let v = 1.0 - v.clamp(0.0, 1.0);
 
@@ -38,6 +39,6 @@ impl Texture for ImageTexture {
         };
 
         let pixel = self.data[j * self.width + i];
-        return Color::from_rgb(pixel.r, pixel.g, pixel.b);
+        Color::from_rgb(pixel.r, pixel.g, pixel.b)
     }
 }
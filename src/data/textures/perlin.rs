@@ -1,6 +1,7 @@
-use rand::{random, Rng, SeedableRng};
+use rand::seq::SliceRandom;
 
 use crate::data::{Color, Point3, Vec3};
+use crate::util::rng::RtRng;
 
 use super::Texture;
 
@@ -11,52 +12,26 @@ pub struct Perlin {
     perm_x: Vec<i64>,
     perm_y: Vec<i64>,
     perm_z: Vec<i64>,
-    rng: rand::rngs::StdRng,
 }
 
 impl Perlin {
-    pub fn empty() -> Perlin {
+    pub fn new(rng: &mut RtRng) -> Perlin {
         Perlin {
-            ranvec: Vec::new(),
-            perm_x: Vec::new(),
-            perm_y: Vec::new(),
-            perm_z: Vec::new(),
-            rng: rand::rngs::StdRng::from_entropy(),
+            ranvec: (0..POINT_COUNT)
+                .map(|_| Vec3::rand_range(-1.0, 1.0, rng))
+                .collect(),
+            perm_x: Perlin::generate_perm(rng),
+            perm_y: Perlin::generate_perm(rng),
+            perm_z: Perlin::generate_perm(rng),
         }
     }
 
-    pub fn new() -> Perlin {
-        let mut p = Perlin::empty();
-        let mut ranvec = vec![Vec3::zero(); POINT_COUNT];
-        for i in 0..POINT_COUNT {
-            ranvec[i] = Vec3::rand_range(-1.0, 1.0);
-        }
-        p.ranvec = ranvec;
-        p.perm_x = p.generate_perm();
-        p.perm_y = p.generate_perm();
-        p.perm_z = p.generate_perm();
+    fn generate_perm(rng: &mut RtRng) -> Vec<i64> {
+        let mut p: Vec<i64> = (0..POINT_COUNT as i64).collect();
+        p.shuffle(rng);
         p
     }
 
-    fn generate_perm(&mut self) -> Vec<i64> {
-        let mut p: Vec<i64> = vec![0; POINT_COUNT];
-        for i in 0..POINT_COUNT {
-            p[i] = i as i64
-        }
-
-        self.permute(&mut p, POINT_COUNT);
-        p
-    }
-
-    fn permute(&mut self, p: &mut Vec<i64>, n: usize) {
-        for i in (1..n).rev() {
-            let target = self.rng.gen_range(0..i);
-            let temp = p[i];
-            p[i] = p[target];
-            p[target] = temp;
-        }
-    }
-
     fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
         let mut accum = 0.0;
 
@@ -64,10 +39,9 @@ impl Perlin {
         let vv = v * v * (3.0 - 2.0 * v);
         let ww = w * w * (3.0 - 2.0 * w);
 
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
-                    let c_ijk = c[i][j][k];
+        for (i, c_i) in c.iter().enumerate() {
+            for (j, c_ij) in c_i.iter().enumerate() {
+                for (k, c_ijk) in c_ij.iter().enumerate() {
                     let weight_v = Vec3::new(u - i as f64, v - j as f64, w - k as f64);
                     let (i, j, k) = (i as f64, j as f64, k as f64);
                     accum += (i * uu + (1.0 - i) * (1.0 - uu))
@@ -81,9 +55,9 @@ impl Perlin {
     }
 
     pub fn noise(&self, p: &Point3) -> f64 {
-        let mut u = p.x() - p.x().floor();
-        let mut v = p.y() - p.y().floor();
-        let mut w = p.z() - p.z().floor();
+        let u = p.x() - p.x().floor();
+        let v = p.y() - p.y().floor();
+        let w = p.z() - p.z().floor();
 
         let i = p.x().floor() as i32;
         let j = p.y().floor() as i32;
@@ -91,10 +65,10 @@ impl Perlin {
 
         let mut c = [[[Vec3::zero(); 2]; 2]; 2];
 
-        for di in 0..2 {
-            for dj in 0..2 {
-                for dk in 0..2 {
-                    c[di][dj][dk] = self.ranvec[(self.perm_x[((i + di as i32) & 255) as usize]
+        for (di, c_i) in c.iter_mut().enumerate() {
+            for (dj, c_ij) in c_i.iter_mut().enumerate() {
+                for (dk, c_ijk) in c_ij.iter_mut().enumerate() {
+                    *c_ijk = self.ranvec[(self.perm_x[((i + di as i32) & 255) as usize]
                         ^ self.perm_y[((j + dj as i32) & 255) as usize]
                         ^ self.perm_z[((k + dk as i32) & 255) as usize])
                         as usize];
@@ -106,7 +80,7 @@ impl Perlin {
 
     pub fn turb(&self, p: &Point3, depth: i32) -> f64 {
         let mut accum = 0.0;
-        let mut temp_p = p.clone();
+        let mut temp_p = *p;
         let mut weight = 1.0;
 
         for _ in 0..depth {
@@ -124,7 +98,7 @@ pub struct PerlinTexture {
 }
 
 impl Texture for PerlinTexture {
-    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+    fn value(&self, _u: f64, _v: f64, p: &Vec3) -> Color {
         // Color::new(1.0, 1.0, 1.0) * self.noise.turb(&(self.scale * *p), 7)
         Color::new(1.0, 1.0, 1.0)
             * 0.5
@@ -133,9 +107,9 @@ impl Texture for PerlinTexture {
 }
 
 impl PerlinTexture {
-    pub fn new(scale: f64) -> PerlinTexture {
+    pub fn new(scale: f64, rng: &mut RtRng) -> PerlinTexture {
         PerlinTexture {
-            noise: Perlin::new(),
+            noise: Perlin::new(rng),
             scale,
         }
     }
@@ -1,8 +1,5 @@
-use rand::{random, Rng};
-use std::{
-    cmp::min,
-    ops::{self, Index},
-};
+use rand::Rng;
+use std::ops::{self, Index};
 
 const CLOSE_PREC: f64 = 10e-6;
 
@@ -16,8 +13,10 @@ pub struct Vec3 {
 pub type Point3 = Vec3;
 pub type Color = Vec3;
 
-fn rand_float(min: f64, max: f64) -> f64 {
-    min + (max - min) * random::<f64>()
+use crate::util::rng::RtRng;
+
+fn rand_float(min: f64, max: f64, rng: &mut RtRng) -> f64 {
+    min + (max - min) * rng.gen::<f64>()
 }
 
 impl Vec3 {
@@ -39,7 +38,7 @@ impl Vec3 {
         self.x * self.x + self.y * self.y + self.z * self.z
     }
 
-    pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
+    pub const fn new(x: f64, y: f64, z: f64) -> Vec3 {
         Vec3 { x, y, z }
     }
 
@@ -51,44 +50,44 @@ impl Vec3 {
         }
     }
 
-    pub fn random() -> Vec3 {
-        Self::new(random(), random(), random())
+    pub fn random(rng: &mut RtRng) -> Vec3 {
+        Self::new(rng.gen(), rng.gen(), rng.gen())
     }
 
-    pub fn rand_range(min: f64, max: f64) -> Vec3 {
+    pub fn rand_range(min: f64, max: f64, rng: &mut RtRng) -> Vec3 {
         Vec3::new(
-            rand_float(min, max),
-            rand_float(min, max),
-            rand_float(min, max),
+            rand_float(min, max, rng),
+            rand_float(min, max, rng),
+            rand_float(min, max, rng),
         )
     }
 
-    pub fn random_in_unit_sphere() -> Vec3 {
+    pub fn random_in_unit_sphere(rng: &mut RtRng) -> Vec3 {
         loop {
-            let p = Vec3::random();
-            if p.len_sq() <= 1.0 {
+            let p = Vec3::rand_range(-1.0, 1.0, rng);
+            if p.len_sq() >= 1.0 {
                 continue;
             };
             return p;
         }
     }
 
-    pub fn random_unit_vector() -> Vec3 {
-        Vec3::random_in_unit_sphere().unit()
+    pub fn random_unit_vector(rng: &mut RtRng) -> Vec3 {
+        Vec3::random_in_unit_sphere(rng).unit()
     }
 
-    pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
-        let in_unit_sphere = Vec3::random_in_unit_sphere();
+    pub fn random_in_hemisphere(normal: &Vec3, rng: &mut RtRng) -> Vec3 {
+        let in_unit_sphere = Vec3::random_in_unit_sphere(rng);
         if in_unit_sphere.dot(normal) > 0.0 {
-            return in_unit_sphere;
+            in_unit_sphere
         } else {
-            return -1.0 * in_unit_sphere;
+            -1.0 * in_unit_sphere
         }
     }
 
-    pub fn random_in_unit_disk() -> Vec3 {
+    pub fn random_in_unit_disk(rng: &mut RtRng) -> Vec3 {
         loop {
-            let mut p = Vec3::rand_range(-1.0, 1.0);
+            let mut p = Vec3::rand_range(-1.0, 1.0, rng);
             p.z = 0.0;
             if p.len_sq() >= 1.0 {
                 continue;
@@ -129,28 +128,11 @@ impl Vec3 {
     }
 
     pub fn clamp(&self, min: f64, max: f64) -> Vec3 {
-        return Vec3::new(
+        Vec3::new(
             self.x.clamp(min, max),
             self.y.clamp(min, max),
             self.z.clamp(min, max),
-        );
-    }
-
-    pub fn as_color(&self, n_samples: usize) -> [u8; 4] {
-        let scale = 1.0 / n_samples as f64;
-        let s = *self * scale;
-        let s = s.clamp(0.0, 0.999);
-        let (mut r, mut g, mut b) = (s.x, s.y, s.z);
-        r = r.sqrt();
-        g = g.sqrt();
-        b = b.sqrt();
-
-        [
-            (r * 255.999) as u8,
-            (g * 255.999) as u8,
-            (b * 255.999) as u8,
-            255,
-        ]
+        )
     }
 }
 
@@ -236,9 +218,182 @@ impl Index<usize> for Vec3 {
     }
 }
 
+impl Color {
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> Color {
+        Color::new(r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0)
+    }
+
+    // relative luminance of linear Rec. 709 / sRGB primaries
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Mat4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Mat4 {
+        Mat4 { m }
+    }
+
+    pub fn identity() -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Mat4 { m }
+    }
+
+    pub fn translation(offset: &Vec3) -> Mat4 {
+        let mut t = Mat4::identity();
+        t.m[0][3] = offset.x;
+        t.m[1][3] = offset.y;
+        t.m[2][3] = offset.z;
+        t
+    }
+
+    pub fn scaling(factors: &Vec3) -> Mat4 {
+        let mut s = Mat4::identity();
+        s.m[0][0] = factors.x;
+        s.m[1][1] = factors.y;
+        s.m[2][2] = factors.z;
+        s
+    }
+
+    pub fn rotation(axis: &Vec3, degrees: f64) -> Mat4 {
+        let a = axis.unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        Mat4::new([
+            [
+                t * a.x * a.x + cos,
+                t * a.x * a.y - sin * a.z,
+                t * a.x * a.z + sin * a.y,
+                0.0,
+            ],
+            [
+                t * a.x * a.y + sin * a.z,
+                t * a.y * a.y + cos,
+                t * a.y * a.z - sin * a.x,
+                0.0,
+            ],
+            [
+                t * a.x * a.z - sin * a.y,
+                t * a.y * a.z + sin * a.x,
+                t * a.z * a.z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.m[row][col]
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    // Gauss-Jordan elimination with partial pivoting; None for singular
+    // matrices and ones with entries that are not finite
+    pub fn inverse(&self) -> Option<Mat4> {
+        if self.m.iter().flatten().any(|x| !x.is_finite()) {
+            return None;
+        }
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&x, &y| a[x][col].abs().total_cmp(&a[y][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+        Some(Mat4 { m: inv })
+    }
+
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x / w, y / w, z / w)
+        }
+    }
+
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+impl ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+    fn mul(self, _rhs: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * _rhs.m[k][j]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
+
+impl PartialEq for Mat4 {
+    fn eq(&self, other: &Mat4) -> bool {
+        self.m
+            .iter()
+            .flatten()
+            .zip(other.m.iter().flatten())
+            .all(|(&a, &b)| is_close(a, b))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Vec3;
+    use crate::util::rng::seeded_rng;
+
+    use super::{Mat4, Vec3};
 
     #[test]
     fn add() {
@@ -302,10 +457,54 @@ mod tests {
         a *= -1.0;
         assert_eq!(a, Vec3::new(-1.0, -2.0, -3.0));
     }
-}
+    #[test]
+    fn mat_inverse() {
+        let m = Mat4::translation(&Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(&Vec3::new(1.0, 1.0, 0.0), 30.0)
+            * Mat4::scaling(&Vec3::new(2.0, 0.5, 4.0));
+        let inv = m.inverse().unwrap();
+        assert_eq!(m * inv, Mat4::identity());
+        assert_eq!(inv * m, Mat4::identity());
+    }
 
-impl Color {
-    pub fn from_rgb(r: u8, g: u8, b: u8) -> Color {
-        Color::new(r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0)
+    #[test]
+    fn mat_singular() {
+        assert!(Mat4::scaling(&Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Mat4::scaling(&Vec3::new(1.0, f64::NAN, 1.0))
+            .inverse()
+            .is_none());
+        assert!(Mat4::rotation(&Vec3::new(0.0, 1.0, 0.0), f64::INFINITY)
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn mat_transform() {
+        let m = Mat4::translation(&Vec3::new(1.0, 2.0, 3.0))
+            * Mat4::rotation(&Vec3::new(0.0, 1.0, 0.0), 90.0);
+        assert_eq!(
+            m.transform_point(&Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(1.0, 2.0, 2.0)
+        );
+        assert_eq!(
+            m.transform_vector(&Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn unit_sphere_samples_fill_the_ball() {
+        let rng = &mut seeded_rng(5);
+        let samples: Vec<Vec3> = (0..1000)
+            .map(|_| Vec3::random_in_unit_sphere(rng))
+            .collect();
+        assert!(samples.iter().all(|p| p.len_sq() < 1.0));
+        // every octant is reached, not just the positive one
+        for octant in 0..8 {
+            let sign = |bit: usize, x: f64| (octant & bit == 0) == (x >= 0.0);
+            assert!(samples
+                .iter()
+                .any(|p| sign(1, p.x()) && sign(2, p.y()) && sign(4, p.z())));
+        }
     }
 }
@@ -1,13 +1,21 @@
 use std::sync::Arc;
 
-use rand::random;
+use rand::Rng;
 
-use crate::data::{materials::Dielectric, Color, Lambertian, Material, Metal, Point3, Vec3};
-use crate::engine::{HittableList, Sphere};
+use crate::data::{
+    materials::Dielectric, Color, DiffuseLight, Lambertian, Material, Metal, Point3, Vec3,
+};
+use crate::engine::{
+    Background, BoxShape, CameraConfig, Hittable, HittableList, Rotate, Sphere, Translate, XYRect,
+    XZRect, YZRect,
+};
 
+use crate::util::rng::{seeded_rng, RtRng};
+
+use super::scene::Scene;
 use super::textures::{CheckerTexture, ImageTexture, PerlinTexture};
 
-pub fn marble_land() -> Arc<HittableList> {
+pub fn marble_land(rng: &mut RtRng) -> Arc<HittableList> {
     let mut world = HittableList::new();
 
     let checker = Arc::new(CheckerTexture::from_colors(
@@ -24,45 +32,21 @@ pub fn marble_land() -> Arc<HittableList> {
     for a in -11..11 {
         for b in -11..11 {
             let (a, b) = (a as f64, b as f64);
-            let choose_mat: f64 = random();
-            let center = Point3::new(a + 0.9 * random::<f64>(), 0.2, b + 0.9 * random::<f64>());
+            let choose_mat: f64 = rng.gen();
+            let center = Point3::new(a + 0.9 * rng.gen::<f64>(), 0.2, b + 0.9 * rng.gen::<f64>());
 
             if (center - Point3::new(4.0, 0.2, 0.0)).len() > 0.9 {
-                let mut material_ptr: Option<Arc<dyn Material + Send + Sync>> = None;
-                if choose_mat < 0.8 {
-                    let albedo = Color::random() * Color::random();
-                    material_ptr = Some(Arc::new(Lambertian::from_rgb(
-                        albedo.x(),
-                        albedo.y(),
-                        albedo.z(),
-                    )));
-                    world.add(Arc::new(Sphere::new(
-                        center,
-                        0.2,
-                        material_ptr.take().unwrap(),
-                    )));
+                let material_ptr: Arc<dyn Material + Send + Sync> = if choose_mat < 0.8 {
+                    let albedo = Color::random(rng) * Color::random(rng);
+                    Arc::new(Lambertian::from_color(albedo))
                 } else if choose_mat < 0.95 {
-                    let albedo = Color::rand_range(0.5, 1.0);
-                    let fuzz = random::<f64>() * 0.5;
-                    material_ptr = Some(Arc::new(Metal::new(
-                        albedo.x(),
-                        albedo.y(),
-                        albedo.z(),
-                        fuzz,
-                    )));
-                    world.add(Arc::new(Sphere::new(
-                        center,
-                        0.2,
-                        material_ptr.take().unwrap(),
-                    )));
+                    let albedo = Color::rand_range(0.5, 1.0, rng);
+                    let fuzz = rng.gen::<f64>() * 0.5;
+                    Arc::new(Metal::new(albedo.x(), albedo.y(), albedo.z(), fuzz))
                 } else {
-                    material_ptr = Some(Arc::new(Dielectric::new(1.5)));
-                    world.add(Arc::new(Sphere::new(
-                        center,
-                        0.2,
-                        material_ptr.take().unwrap(),
-                    )));
-                }
+                    Arc::new(Dielectric::new(1.5))
+                };
+                world.add(Arc::new(Sphere::new(center, 0.2, material_ptr)));
             }
         }
     }
@@ -120,10 +104,10 @@ pub fn three_balls() -> Arc<HittableList> {
     Arc::new(world)
 }
 
-pub fn balls_perlin() -> Arc<HittableList> {
+pub fn balls_perlin(rng: &mut RtRng) -> Arc<HittableList> {
     let mut world = HittableList::new();
 
-    let perlin = Arc::new(PerlinTexture::new(4.0));
+    let perlin = Arc::new(PerlinTexture::new(4.0, rng));
     world.add(Arc::new(Sphere::new(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
@@ -142,7 +126,9 @@ pub fn balls_perlin() -> Arc<HittableList> {
 pub fn world_map() -> Arc<HittableList> {
     let mut world = HittableList::new();
 
-    let earth = Arc::new(ImageTexture::new("/home/dev/Documents/programming/rust/rust_tracer/res/earthmap.png"));
+    let earth = Arc::new(ImageTexture::new(
+        "/home/dev/Documents/programming/rust/rust_tracer/res/earthmap.png",
+    ));
     world.add(Arc::new(Sphere::new(
         Point3::new(0.0, 0.0, 0.0),
         2.0,
@@ -151,3 +137,146 @@ pub fn world_map() -> Arc<HittableList> {
 
     Arc::new(world)
 }
+
+pub fn simple_light(rng: &mut RtRng) -> Arc<HittableList> {
+    let mut world = HittableList::new();
+
+    let perlin = Arc::new(PerlinTexture::new(4.0, rng));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::from_texture(perlin.clone())),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Arc::new(Lambertian::from_texture(perlin)),
+    )));
+
+    world.add(simple_light_lamp());
+
+    Arc::new(world)
+}
+
+pub fn simple_light_lamp() -> Arc<XYRect> {
+    let light = Arc::new(DiffuseLight::from_rgb(4.0, 4.0, 4.0));
+    Arc::new(XYRect::new(3.0, 5.0, 1.0, 3.0, -2.0, light))
+}
+
+pub fn cornell_box() -> Arc<HittableList> {
+    let mut world = HittableList::new();
+
+    let red = Arc::new(Lambertian::from_rgb(0.65, 0.05, 0.05));
+    let white = Arc::new(Lambertian::from_rgb(0.73, 0.73, 0.73));
+    let green = Arc::new(Lambertian::from_rgb(0.12, 0.45, 0.15));
+
+    world.add(Arc::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    world.add(Arc::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    world.add(cornell_box_lamp());
+    world.add(Arc::new(XZRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+        white.clone(),
+    )));
+    world.add(Arc::new(XZRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+    world.add(Arc::new(XYRect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+
+    let box1 = Arc::new(BoxShape::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    ));
+    let box1 = Arc::new(Rotate::y(box1, 15.0));
+    world.add(Arc::new(Translate::new(box1, Vec3::new(265.0, 0.0, 295.0))));
+
+    let box2 = Arc::new(BoxShape::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        white,
+    ));
+    let box2 = Arc::new(Rotate::y(box2, -18.0));
+    world.add(Arc::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0))));
+
+    Arc::new(world)
+}
+
+pub fn cornell_box_lamp() -> Arc<XZRect> {
+    let light = Arc::new(DiffuseLight::from_rgb(15.0, 15.0, 15.0));
+    Arc::new(XZRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light))
+}
+
+// a list holding just `light`, for sampling it directly
+fn lights(light: Arc<dyn Hittable + Send + Sync>) -> Arc<HittableList> {
+    let mut lights = HittableList::new();
+    lights.add(light);
+    Arc::new(lights)
+}
+
+pub const BUILTIN_NAMES: [&str; 6] = [
+    "marble_land",
+    "three_balls",
+    "balls_perlin",
+    "world_map",
+    "simple_light",
+    "cornell_box",
+];
+
+pub fn builtin(name: &str, seed: u64) -> Option<Scene> {
+    let rng = &mut seeded_rng(seed);
+    let scene = match name {
+        "marble_land" => Scene::new(marble_land(rng).build()),
+        "three_balls" => {
+            let mut scene = Scene::new(three_balls().build());
+            scene.camera.look_from = Point3::new(3.0, 3.0, 2.0);
+            scene.camera.look_at = Point3::new(0.0, 0.0, -1.0);
+            scene.camera.focus_dist = (scene.camera.look_from - scene.camera.look_at).len();
+            scene.camera.vfov = 40.0;
+            scene
+        }
+        "balls_perlin" => Scene::new(balls_perlin(rng).build()),
+        "world_map" => Scene::new(world_map().build()),
+        "simple_light" => {
+            let mut scene = Scene::new(simple_light(rng).build());
+            scene.lights = lights(simple_light_lamp());
+            scene.camera.look_from = Point3::new(26.0, 3.0, 6.0);
+            scene.camera.look_at = Point3::new(0.0, 2.0, 0.0);
+            scene.settings.background = Background::Solid(Color::zero());
+            scene.settings.samples_per_pixel = 400;
+            scene
+        }
+        "cornell_box" => {
+            let mut scene = Scene::new(cornell_box().build());
+            scene.lights = lights(cornell_box_lamp());
+            scene.camera = CameraConfig {
+                look_from: Point3::new(278.0, 278.0, -800.0),
+                look_at: Point3::new(278.0, 278.0, 0.0),
+                vfov: 40.0,
+                ..CameraConfig::default()
+            };
+            scene.settings.background = Background::Solid(Color::zero());
+            scene.settings.width = 600;
+            scene.settings.height = 600;
+            scene.settings.samples_per_pixel = 200;
+            scene
+        }
+        _ => return None,
+    };
+    Some(scene)
+}
@@ -15,6 +15,40 @@ impl AABB {
         AABB { min, max }
     }
 
+    pub fn min(&self) -> Point3 {
+        self.min
+    }
+
+    pub fn max(&self) -> Point3 {
+        self.max
+    }
+
+    pub fn corners(&self) -> [Point3; 8] {
+        let mut corners = [Point3::zero(); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let pick = |bit: usize, axis: usize| {
+                if i & bit == 0 {
+                    self.min[axis]
+                } else {
+                    self.max[axis]
+                }
+            };
+            *corner = Point3::new(pick(1, 0), pick(2, 1), pick(4, 2));
+        }
+        corners
+    }
+
+    pub fn transformed<F: Fn(&Point3) -> Point3>(&self, f: F) -> AABB {
+        let corners = self.corners();
+        let first = f(&corners[0]);
+        let mut bbox = AABB::new(first, first);
+        for p in corners.iter() {
+            let p = f(p);
+            bbox = AABB::surrounding_box(&bbox, &AABB::new(p, p));
+        }
+        bbox
+    }
+
     pub fn empty() -> AABB {
         AABB {
             min: Point3::zero(),
@@ -61,8 +95,11 @@ impl Hittable for AABB {
         let mut t_max = t_max;
         for a in 0..3 {
             let inv_d = 1.0 / r.dir()[a];
-            let t0 = (self.min[a] - r.origin()[a]) * inv_d;
-            let t1 = (self.max[a] - r.origin()[a]) * inv_d;
+            let mut t0 = (self.min[a] - r.origin()[a]) * inv_d;
+            let mut t1 = (self.max[a] - r.origin()[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
             t_min = f64::max(t0, t_min);
             t_max = f64::min(t1, t_max);
             if t_max <= t_min {
@@ -73,6 +110,36 @@ impl Hittable for AABB {
     }
 
     fn bounding_box(&self, output_box: &mut AABB) -> bool {
-        todo!()
+        *output_box = self.clone();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Point3, Vec3};
+    use crate::engine::{HitRecord, Hittable, Ray};
+
+    use super::AABB;
+
+    #[test]
+    fn slabs_are_hit_from_either_direction() {
+        let bbox = AABB::new(Point3::zero(), Point3::new(1.0, 1.0, 1.0));
+        let mut rec = HitRecord::empty();
+        for &(origin, dir) in [
+            (Point3::new(0.5, 0.5, 2.0), Vec3::new(0.0, 0.0, -1.0)),
+            (Point3::new(0.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0)),
+            (Point3::new(2.0, 2.0, 2.0), Vec3::new(-1.0, -1.0, -1.0)),
+        ]
+        .iter()
+        {
+            assert!(bbox.hit(&Ray::new(origin, dir), 0.001, f64::INFINITY, &mut rec));
+            let away = Ray::new(origin, dir * -1.0);
+            assert!(!bbox.hit(&away, 0.001, f64::INFINITY, &mut rec));
+        }
+
+        let mut copy = AABB::empty();
+        assert!(bbox.bounding_box(&mut copy));
+        assert_eq!((copy.min(), copy.max()), (bbox.min(), bbox.max()));
     }
 }
@@ -0,0 +1,314 @@
+use std::sync::Arc;
+
+use crate::data::{Material, Point3, Vec3};
+use crate::engine::hittable::{HitRecord, Hittable};
+
+use super::{Ray, AABB};
+
+const PADDING: f64 = 0.0001;
+
+// Density per solid angle of picking `direction` from `origin` by sampling
+// a point on the rectangle uniformly.
+fn rect_pdf(rect: &dyn Hittable, area: f64, origin: &Point3, direction: &Vec3) -> f64 {
+    let mut rec = HitRecord::empty();
+    if !rect.hit(
+        &Ray::new(*origin, *direction),
+        0.001,
+        f64::INFINITY,
+        &mut rec,
+    ) {
+        return 0.0;
+    }
+    let distance_sq = rec.t * rec.t * direction.len_sq();
+    let cosine = direction.dot(&rec.normal).abs() / direction.len();
+    distance_sq / (cosine * area)
+}
+
+pub struct XYRect {
+    x0: f64,
+    x1: f64,
+    y0: f64,
+    y1: f64,
+    k: f64,
+    mat_ptr: Arc<dyn Material + Send + Sync>,
+}
+
+pub struct XZRect {
+    x0: f64,
+    x1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+    mat_ptr: Arc<dyn Material + Send + Sync>,
+}
+
+pub struct YZRect {
+    y0: f64,
+    y1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+    mat_ptr: Arc<dyn Material + Send + Sync>,
+}
+
+impl XYRect {
+    pub fn new(
+        x0: f64,
+        x1: f64,
+        y0: f64,
+        y1: f64,
+        k: f64,
+        mat_ptr: Arc<dyn Material + Send + Sync>,
+    ) -> XYRect {
+        XYRect {
+            x0,
+            x1,
+            y0,
+            y1,
+            k,
+            mat_ptr,
+        }
+    }
+}
+
+impl XZRect {
+    pub fn new(
+        x0: f64,
+        x1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        mat_ptr: Arc<dyn Material + Send + Sync>,
+    ) -> XZRect {
+        XZRect {
+            x0,
+            x1,
+            z0,
+            z1,
+            k,
+            mat_ptr,
+        }
+    }
+}
+
+impl YZRect {
+    pub fn new(
+        y0: f64,
+        y1: f64,
+        z0: f64,
+        z1: f64,
+        k: f64,
+        mat_ptr: Arc<dyn Material + Send + Sync>,
+    ) -> YZRect {
+        YZRect {
+            y0,
+            y1,
+            z0,
+            z1,
+            k,
+            mat_ptr,
+        }
+    }
+}
+
+impl Hittable for XYRect {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = (self.k - ray.origin().z()) / ray.dir().z();
+        if !(t_min..=t_max).contains(&t) {
+            return false;
+        }
+        let x = ray.origin().x() + t * ray.dir().x();
+        let y = ray.origin().y() + t * ray.dir().y();
+        if x < self.x0 || x > self.x1 || y < self.y0 || y > self.y1 {
+            return false;
+        }
+        rec.u = (x - self.x0) / (self.x1 - self.x0);
+        rec.v = (y - self.y0) / (self.y1 - self.y0);
+        rec.t = t;
+        rec.set_face_normal(ray, &Vec3::new(0.0, 0.0, 1.0));
+        rec.mat_ptr = Arc::clone(&self.mat_ptr);
+        rec.p = ray.at(t);
+        true
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        *output_box = AABB::new(
+            Point3::new(self.x0, self.y0, self.k - PADDING),
+            Point3::new(self.x1, self.y1, self.k + PADDING),
+        );
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.x1 - self.x0) * (self.y1 - self.y0);
+        rect_pdf(self, area, origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        let point = Point3::new(
+            self.x0 + u.0 * (self.x1 - self.x0),
+            self.y0 + u.1 * (self.y1 - self.y0),
+            self.k,
+        );
+        point - *origin
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        out.push(Arc::clone(&self.mat_ptr));
+    }
+}
+
+impl Hittable for XZRect {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = (self.k - ray.origin().y()) / ray.dir().y();
+        if !(t_min..=t_max).contains(&t) {
+            return false;
+        }
+        let x = ray.origin().x() + t * ray.dir().x();
+        let z = ray.origin().z() + t * ray.dir().z();
+        if x < self.x0 || x > self.x1 || z < self.z0 || z > self.z1 {
+            return false;
+        }
+        rec.u = (x - self.x0) / (self.x1 - self.x0);
+        rec.v = (z - self.z0) / (self.z1 - self.z0);
+        rec.t = t;
+        rec.set_face_normal(ray, &Vec3::new(0.0, 1.0, 0.0));
+        rec.mat_ptr = Arc::clone(&self.mat_ptr);
+        rec.p = ray.at(t);
+        true
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        *output_box = AABB::new(
+            Point3::new(self.x0, self.k - PADDING, self.z0),
+            Point3::new(self.x1, self.k + PADDING, self.z1),
+        );
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.x1 - self.x0) * (self.z1 - self.z0);
+        rect_pdf(self, area, origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        let point = Point3::new(
+            self.x0 + u.0 * (self.x1 - self.x0),
+            self.k,
+            self.z0 + u.1 * (self.z1 - self.z0),
+        );
+        point - *origin
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        out.push(Arc::clone(&self.mat_ptr));
+    }
+}
+
+impl Hittable for YZRect {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let t = (self.k - ray.origin().x()) / ray.dir().x();
+        if !(t_min..=t_max).contains(&t) {
+            return false;
+        }
+        let y = ray.origin().y() + t * ray.dir().y();
+        let z = ray.origin().z() + t * ray.dir().z();
+        if y < self.y0 || y > self.y1 || z < self.z0 || z > self.z1 {
+            return false;
+        }
+        rec.u = (y - self.y0) / (self.y1 - self.y0);
+        rec.v = (z - self.z0) / (self.z1 - self.z0);
+        rec.t = t;
+        rec.set_face_normal(ray, &Vec3::new(1.0, 0.0, 0.0));
+        rec.mat_ptr = Arc::clone(&self.mat_ptr);
+        rec.p = ray.at(t);
+        true
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        *output_box = AABB::new(
+            Point3::new(self.k - PADDING, self.y0, self.z0),
+            Point3::new(self.k + PADDING, self.y1, self.z1),
+        );
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.y1 - self.y0) * (self.z1 - self.z0);
+        rect_pdf(self, area, origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        let point = Point3::new(
+            self.k,
+            self.y0 + u.0 * (self.y1 - self.y0),
+            self.z0 + u.1 * (self.z1 - self.z0),
+        );
+        point - *origin
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        out.push(Arc::clone(&self.mat_ptr));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Lambertian, Point3};
+    use crate::engine::{HitRecord, Hittable, Ray, AABB};
+
+    use super::{XYRect, XZRect, YZRect};
+
+    #[test]
+    fn rects_are_hit_inside_their_bounds_only() {
+        let mat = Lambertian::black_sh();
+        let rects: [(Box<dyn Hittable>, usize); 3] = [
+            (
+                Box::new(XYRect::new(0.0, 2.0, 0.0, 4.0, 1.0, mat.clone())),
+                2,
+            ),
+            (
+                Box::new(XZRect::new(0.0, 2.0, 0.0, 4.0, 1.0, mat.clone())),
+                1,
+            ),
+            (Box::new(YZRect::new(0.0, 2.0, 0.0, 4.0, 1.0, mat)), 0),
+        ];
+        for (rect, axis) in rects.iter() {
+            // a point at (1, 3) within the rect's plane and k along its normal
+            let point = |a: f64, b: f64, k: f64| {
+                let mut p = [0.0; 3];
+                let (i, j) = match axis {
+                    0 => (1, 2),
+                    1 => (0, 2),
+                    _ => (0, 1),
+                };
+                p[i] = a;
+                p[j] = b;
+                p[*axis] = k;
+                Point3::new(p[0], p[1], p[2])
+            };
+            let dir = point(0.0, 0.0, 1.0);
+
+            let mut rec = HitRecord::empty();
+            let ray = Ray::new(point(1.0, 3.0, -2.0), dir);
+            assert!(rect.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+            assert_eq!(rec.t, 3.0);
+            assert_eq!(rec.p, point(1.0, 3.0, 1.0));
+            assert_eq!((rec.u, rec.v), (0.5, 0.75));
+            // the rect faces +k, so a ray coming from below hits its back
+            assert!(!rec.front_face);
+            assert_eq!(rec.normal, dir * -1.0);
+
+            let outside = Ray::new(point(3.0, 3.0, -2.0), dir);
+            assert!(!rect.hit(&outside, 0.001, f64::INFINITY, &mut rec));
+            assert!(!rect.hit(&ray, 0.001, 2.0, &mut rec));
+
+            let mut bbox = AABB::empty();
+            assert!(rect.bounding_box(&mut bbox));
+            // padded along the normal so the box is never flat
+            assert!(bbox.min()[*axis] < 1.0 && bbox.max()[*axis] > 1.0);
+            assert_eq!(bbox.min(), point(0.0, 0.0, bbox.min()[*axis]));
+            assert_eq!(bbox.max(), point(2.0, 4.0, bbox.max()[*axis]));
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use crate::data::{Color, Vec3};
+
+use super::Ray;
+
+#[derive(Clone, Copy)]
+pub enum Background {
+    Sky,
+    Solid(Color),
+}
+
+impl Background {
+    pub fn value(&self, r: &Ray) -> Color {
+        match self {
+            Background::Sky => {
+                let unit_dir = r.dir().unit();
+                let t = 0.5 * (unit_dir.y() + 1.0);
+                (1.0 - t) * Vec3::new(1.0, 1.0, 1.0) + t * Vec3::new(0.5, 0.7, 1.0)
+            }
+            Background::Solid(color) => *color,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use crate::data::{Material, Point3};
+use crate::engine::hittable::{HitRecord, Hittable};
+
+use super::{HittableList, Ray, XYRect, XZRect, YZRect, AABB};
+
+pub struct BoxShape {
+    box_min: Point3,
+    box_max: Point3,
+    sides: HittableList,
+}
+
+impl BoxShape {
+    pub fn new(p0: Point3, p1: Point3, mat_ptr: Arc<dyn Material + Send + Sync>) -> BoxShape {
+        let box_min = Point3::new(
+            f64::min(p0.x(), p1.x()),
+            f64::min(p0.y(), p1.y()),
+            f64::min(p0.z(), p1.z()),
+        );
+        let box_max = Point3::new(
+            f64::max(p0.x(), p1.x()),
+            f64::max(p0.y(), p1.y()),
+            f64::max(p0.z(), p1.z()),
+        );
+        let (p0, p1) = (box_min, box_max);
+
+        let mut sides = HittableList::new();
+        sides.add(Arc::new(XYRect::new(
+            p0.x(),
+            p1.x(),
+            p0.y(),
+            p1.y(),
+            p1.z(),
+            Arc::clone(&mat_ptr),
+        )));
+        sides.add(Arc::new(XYRect::new(
+            p0.x(),
+            p1.x(),
+            p0.y(),
+            p1.y(),
+            p0.z(),
+            Arc::clone(&mat_ptr),
+        )));
+        sides.add(Arc::new(XZRect::new(
+            p0.x(),
+            p1.x(),
+            p0.z(),
+            p1.z(),
+            p1.y(),
+            Arc::clone(&mat_ptr),
+        )));
+        sides.add(Arc::new(XZRect::new(
+            p0.x(),
+            p1.x(),
+            p0.z(),
+            p1.z(),
+            p0.y(),
+            Arc::clone(&mat_ptr),
+        )));
+        sides.add(Arc::new(YZRect::new(
+            p0.y(),
+            p1.y(),
+            p0.z(),
+            p1.z(),
+            p1.x(),
+            Arc::clone(&mat_ptr),
+        )));
+        sides.add(Arc::new(YZRect::new(
+            p0.y(),
+            p1.y(),
+            p0.z(),
+            p1.z(),
+            p0.x(),
+            mat_ptr,
+        )));
+
+        BoxShape {
+            box_min,
+            box_max,
+            sides,
+        }
+    }
+}
+
+impl Hittable for BoxShape {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.sides.hit(ray, t_min, t_max, rec)
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        *output_box = AABB::new(self.box_min, self.box_max);
+        true
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        self.sides.materials(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Lambertian, Point3, Vec3};
+    use crate::engine::{HitRecord, Hittable, Ray, AABB};
+
+    use super::BoxShape;
+
+    #[test]
+    fn box_is_hit_on_its_nearest_face() {
+        // corners given out of order still make the same box
+        let shape = BoxShape::new(
+            Point3::new(1.0, 0.0, 3.0),
+            Point3::new(0.0, 2.0, 0.0),
+            Lambertian::black_sh(),
+        );
+
+        let mut rec = HitRecord::empty();
+        let ray = Ray::new(Point3::new(-1.0, 1.0, 1.5), Vec3::new(1.0, 0.0, 0.0));
+        assert!(shape.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.t, 1.0);
+        assert_eq!(rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+
+        // from inside only the far face is in front of the ray, and its
+        // normal still faces the ray
+        let ray = Ray::new(Point3::new(0.5, 1.0, 1.5), Vec3::new(0.0, 0.0, -1.0));
+        assert!(shape.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.p, Point3::new(0.5, 1.0, 0.0));
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        let ray = Ray::new(Point3::new(-1.0, 2.5, 1.5), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!shape.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+
+        let mut bbox = AABB::empty();
+        assert!(shape.bounding_box(&mut bbox));
+        assert_eq!(bbox.min(), Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(bbox.max(), Point3::new(1.0, 2.0, 3.0));
+    }
+}
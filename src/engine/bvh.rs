@@ -2,7 +2,10 @@ use std::{cmp::Ordering, sync::Arc};
 
 use rand::Rng;
 
-use super::{Hittable, AABB};
+use crate::data::Material;
+use crate::util::rng::RtRng;
+
+use super::{HitRecord, Hittable, Ray, AABB};
 
 pub struct BVHnode {
     left: Arc<dyn Hittable + Send + Sync>,
@@ -15,10 +18,10 @@ impl BVHnode {
         src_objects: &mut Vec<Arc<dyn Hittable + Send + Sync>>,
         start: usize,
         end: usize,
+        rng: &mut RtRng,
     ) -> BVHnode {
-        let mut rng = rand::thread_rng();
         let objects = src_objects;
-        let axis = rng.gen_range(start..=end);
+        let axis = rng.gen_range(0..3);
         let comparator = |a, b| AABB::box_cmp(a, b, axis);
         let object_span = end - start;
 
@@ -29,9 +32,9 @@ impl BVHnode {
                 _ => &objects[start + 1],
             }),
             _ => {
-                objects.sort_by(|a, b| AABB::box_cmp(a, b, axis));
+                objects[start..end].sort_by(|a, b| AABB::box_cmp(a, b, axis));
                 let mid = start + object_span / 2;
-                Arc::new(BVHnode::new(objects, start, mid))
+                Arc::new(BVHnode::new(objects, start, mid, rng))
             }
         };
         let right = match object_span {
@@ -42,7 +45,7 @@ impl BVHnode {
             }),
             _ => {
                 let mid = start + object_span / 2;
-                Arc::new(BVHnode::new(objects, mid, end))
+                Arc::new(BVHnode::new(objects, mid, end, rng))
             }
         };
 
@@ -58,20 +61,105 @@ impl BVHnode {
             my_box: AABB::surrounding_box(&box_left, &box_right),
         }
     }
-}
 
-impl Hittable for BVHnode {
-    fn hit(&self, ray: &super::Ray, t_min: f64, t_max: f64, rec: &mut super::HitRecord) -> bool {
+    // tests the children with `hit`, the nearer hit first if there are two
+    fn hit_children<F>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        mut hit: F,
+    ) -> bool
+    where
+        F: FnMut(&(dyn Hittable + Send + Sync), f64, &mut HitRecord) -> bool,
+    {
         if !self.my_box.hit(ray, t_min, t_max, rec) {
             return false;
         }
-        let hit_left = self.left.hit(ray, t_min, t_max, rec);
-        let hit_right = self.right.hit(ray, t_min, t_max, rec);
+        let hit_left = hit(&*self.left, t_max, rec);
+        let t_max = if hit_left { rec.t } else { t_max };
+        let hit_right = hit(&*self.right, t_max, rec);
         hit_left || hit_right
     }
+}
+
+impl Hittable for BVHnode {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.hit_children(ray, t_min, t_max, rec, |child, t_max, rec| {
+            child.hit(ray, t_min, t_max, rec)
+        })
+    }
+
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        visits: &mut usize,
+    ) -> bool {
+        *visits += 1;
+        self.hit_children(ray, t_min, t_max, rec, |child, t_max, rec| {
+            child.hit_counting(ray, t_min, t_max, rec, visits)
+        })
+    }
 
     fn bounding_box(&self, output_box: &mut AABB) -> bool {
         *output_box = self.my_box.clone();
         true
     }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        self.left.materials(out);
+        self.right.materials(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::Rng;
+
+    use crate::data::{Lambertian, Vec3};
+    use crate::engine::{HitRecord, Hittable, HittableList, Ray, Sphere};
+    use crate::util::rng::seeded_rng;
+
+    use super::BVHnode;
+
+    #[test]
+    fn finds_the_same_hits_as_a_list() {
+        let rng = &mut seeded_rng(3);
+        let mut list = HittableList::new();
+        let mut objects: Vec<Arc<dyn Hittable + Send + Sync>> = Vec::new();
+        for _ in 0..50 {
+            let center = Vec3::rand_range(-10.0, 10.0, rng);
+            let sphere = Arc::new(Sphere::new(center, rng.gen(), Lambertian::black_sh()));
+            list.add(sphere.clone());
+            objects.push(sphere);
+        }
+        let len = objects.len();
+        let bvh = BVHnode::new(&mut objects, 0, len, rng);
+
+        for _ in 0..1000 {
+            let ray = Ray::new(
+                Vec3::rand_range(-15.0, 15.0, rng),
+                Vec3::rand_range(-1.0, 1.0, rng),
+            );
+            let mut expected = HitRecord::empty();
+            let mut actual = HitRecord::empty();
+            let hit_list = list.hit(&ray, 0.001, f64::INFINITY, &mut expected);
+            assert_eq!(bvh.hit(&ray, 0.001, f64::INFINITY, &mut actual), hit_list);
+            if hit_list {
+                assert_eq!(actual.t, expected.t);
+            }
+
+            // counting finds the same hit and at least tests the root
+            let mut visits = 0;
+            let hit = bvh.hit_counting(&ray, 0.001, f64::INFINITY, &mut actual, &mut visits);
+            assert_eq!(hit, hit_list);
+            assert!(visits >= 1 && visits < 2 * len);
+        }
+    }
 }
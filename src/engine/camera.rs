@@ -1,6 +1,8 @@
 use super::Ray;
 use crate::data::{Point3, Vec3};
+use crate::util::sampler::{sample_disk, Sampler};
 
+#[derive(Clone)]
 pub struct Camera {
     origin: Point3,
     lower_left_corner: Point3,
@@ -8,10 +10,46 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
-    w: Vec3,
     lens_radius: f64,
 }
 
+#[derive(Clone)]
+pub struct CameraConfig {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub up: Vec3,
+    pub vfov: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+}
+
+impl CameraConfig {
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            self.look_from,
+            self.look_at,
+            self.up,
+            aspect_ratio,
+            self.vfov,
+            self.aperture,
+            self.focus_dist,
+        )
+    }
+}
+
+impl Default for CameraConfig {
+    fn default() -> CameraConfig {
+        CameraConfig {
+            look_from: Point3::new(13.0, 2.0, 3.0),
+            look_at: Point3::new(0.0, 0.0, 0.0),
+            up: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 20.0,
+            aperture: 0.0,
+            focus_dist: 10.0,
+        }
+    }
+}
+
 impl Camera {
     pub fn new(
         look_from: Point3,
@@ -26,7 +64,6 @@ impl Camera {
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h;
         let viewport_width = aspect_ratio * viewport_height;
-        let focal_length = 1.0;
 
         let w = (look_from - look_at).unit();
         let u = up.cross(&w);
@@ -44,14 +81,13 @@ impl Camera {
             vertical,
             lower_left_corner,
             lens_radius,
-            w,
             u,
             v,
         }
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * sample_disk(sampler.get_2d());
         let offset = self.u * rd.x() + self.v * rd.y();
         Ray::new(
             self.origin + offset,
@@ -15,6 +15,10 @@ pub struct HitRecord {
     pub mat_ptr: Arc<dyn Material + Send + Sync>,
     pub u: f64,
     pub v: f64,
+    // index + 1 of the top-level object that was hit and of its material
+    // among all materials in the scene, 0 if not known
+    pub object_id: usize,
+    pub material_id: usize,
 }
 
 impl HitRecord {
@@ -35,6 +39,8 @@ impl HitRecord {
             mat_ptr: Lambertian::black_sh(),
             u: 0.0,
             v: 0.0,
+            object_id: 0,
+            material_id: 0,
         }
     }
 }
@@ -42,4 +48,32 @@ impl HitRecord {
 pub trait Hittable {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
     fn bounding_box(&self, output_box: &mut AABB) -> bool;
+
+    // `hit`, also adding the bounding volume nodes it tests to `visits`. Only
+    // the BVH cost view counts, so shapes holding others override this and
+    // leave `hit` as it is.
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        _visits: &mut usize,
+    ) -> bool {
+        self.hit(ray, t_min, t_max, rec)
+    }
+
+    // Density, per unit solid angle seen from `origin`, with which `random`
+    // picks `direction`. Shapes that cannot be sampled as lights return 0.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // direction from `origin` towards a point on the shape picked with `u`
+    fn random(&self, _origin: &Point3, _u: (f64, f64)) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    // every material the shape can put in a hit record, in a fixed order
+    fn materials(&self, _out: &mut Vec<Arc<dyn Material + Send + Sync>>) {}
 }
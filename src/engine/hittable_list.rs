@@ -1,5 +1,8 @@
-use super::{ray::Ray, AABB};
+use super::{ray::Ray, BVHnode, AABB};
+use crate::data::{Material, Point3, Vec3};
 use crate::engine::hittable::{HitRecord, Hittable};
+use crate::util::rng::seeded_rng;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 type RTTrait = dyn Hittable + Send + Sync;
@@ -22,16 +25,135 @@ impl HittableList {
     pub fn add(&mut self, object: Arc<RTTrait>) {
         self.objects.push(object);
     }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    // The objects ready to render as a scene's world: numbered from 1 in the
+    // order they were added, and their materials in the order they first
+    // appear, so hits report the same ids in every run. Objects with bounds
+    // go in a BVH, the rest are tested one by one next to it.
+    pub fn build(&self) -> Arc<RTTrait> {
+        let mut ids = HashMap::new();
+        let mut bounded = Vec::new();
+        let mut world = HittableList::new();
+        for (index, object) in self.objects.iter().enumerate() {
+            let mut materials = Vec::new();
+            object.materials(&mut materials);
+            let mut own = Vec::new();
+            for material in materials.iter() {
+                let address = address(material);
+                let next = ids.len() + 1;
+                let id = *ids.entry(address).or_insert(next);
+                if !own.contains(&(address, id)) {
+                    own.push((address, id));
+                }
+            }
+            let identified: Arc<RTTrait> = Arc::new(Identified {
+                object: Arc::clone(object),
+                object_id: index + 1,
+                materials: own,
+            });
+            if identified.bounding_box(&mut AABB::empty()) {
+                bounded.push(identified);
+            } else {
+                world.add(identified);
+            }
+        }
+        match bounded.len() {
+            0 => {}
+            1 => world.add(bounded.pop().unwrap()),
+            count => world.add(Arc::new(BVHnode::new(
+                &mut bounded,
+                0,
+                count,
+                &mut seeded_rng(0),
+            ))),
+        }
+        Arc::new(world)
+    }
 }
 
-impl Hittable for HittableList {
+fn address(material: &Arc<dyn Material + Send + Sync>) -> usize {
+    Arc::as_ptr(material) as *const () as usize
+}
+
+// Puts the ids of its object and of the material hit in every hit record.
+struct Identified {
+    object: Arc<RTTrait>,
+    object_id: usize,
+    // address and id of each of the object's materials
+    materials: Vec<(usize, usize)>,
+}
+
+impl Identified {
+    fn identify(&self, rec: &mut HitRecord, hit: bool) -> bool {
+        if !hit {
+            return false;
+        }
+        let material = address(&rec.mat_ptr);
+        rec.object_id = self.object_id;
+        rec.material_id = self
+            .materials
+            .iter()
+            .find(|&&(address, _)| address == material)
+            .map_or(0, |&(_, id)| id);
+        true
+    }
+}
+
+impl Hittable for Identified {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let hit = self.object.hit(ray, t_min, t_max, rec);
+        self.identify(rec, hit)
+    }
+
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        visits: &mut usize,
+    ) -> bool {
+        let hit = self.object.hit_counting(ray, t_min, t_max, rec, visits);
+        self.identify(rec, hit)
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        self.object.bounding_box(output_box)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        self.object.random(origin, u)
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        self.object.materials(out);
+    }
+}
+
+impl HittableList {
+    // the closest hit among the objects, each tested with `hit`
+    fn hit_objects<F>(&self, t_max: f64, rec: &mut HitRecord, mut hit: F) -> bool
+    where
+        F: FnMut(&RTTrait, f64, &mut HitRecord) -> bool,
+    {
         let mut temp_rec = HitRecord::empty();
         let mut hit_anything = false;
         let mut closest = t_max;
 
-        for i in self.objects.iter() {
-            if i.hit(ray, t_min, closest, &mut temp_rec) {
+        for object in self.objects.iter() {
+            if hit(&**object, closest, &mut temp_rec) {
                 hit_anything = true;
                 closest = temp_rec.t;
                 *rec = temp_rec.clone();
@@ -39,6 +161,33 @@ impl Hittable for HittableList {
         }
         hit_anything
     }
+}
+
+impl Default for HittableList {
+    fn default() -> HittableList {
+        HittableList::new()
+    }
+}
+
+impl Hittable for HittableList {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.hit_objects(t_max, rec, |object, t_max, rec| {
+            object.hit(ray, t_min, t_max, rec)
+        })
+    }
+
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        visits: &mut usize,
+    ) -> bool {
+        self.hit_objects(t_max, rec, |object, t_max, rec| {
+            object.hit_counting(ray, t_min, t_max, rec, visits)
+        })
+    }
 
     fn bounding_box(&self, output_box: &mut AABB) -> bool {
         if self.objects.is_empty() {
@@ -61,4 +210,32 @@ impl Hittable for HittableList {
         }
         true
     }
+    // Samples one of the objects, all equally likely, so a list of lights
+    // can be sampled as a whole.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        // the first coordinate picks the object and is then reused
+        let scaled = u.0 * self.objects.len() as f64;
+        let i = (scaled as usize).min(self.objects.len() - 1);
+        self.objects[i].random(origin, (scaled - i as f64, u.1))
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        for object in self.objects.iter() {
+            object.materials(out);
+        }
+    }
 }
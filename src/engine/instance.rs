@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use crate::data::{Material, Vec3};
+use crate::engine::hittable::{HitRecord, Hittable};
+
+use super::{Ray, AABB};
+
+pub struct Translate {
+    ptr: Arc<dyn Hittable + Send + Sync>,
+    offset: Vec3,
+}
+
+impl Translate {
+    pub fn new(ptr: Arc<dyn Hittable + Send + Sync>, offset: Vec3) -> Translate {
+        Translate { ptr, offset }
+    }
+
+    // `hit` tests the moved ray against the inner shape
+    fn hit_moved<F>(&self, ray: &Ray, rec: &mut HitRecord, hit: F) -> bool
+    where
+        F: FnOnce(&Ray, &mut HitRecord) -> bool,
+    {
+        let moved = Ray::new(*ray.origin() - self.offset, *ray.dir());
+        if !hit(&moved, rec) {
+            return false;
+        }
+        rec.p += self.offset;
+        true
+    }
+}
+
+impl Hittable for Translate {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.hit_moved(ray, rec, |ray, rec| self.ptr.hit(ray, t_min, t_max, rec))
+    }
+
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        visits: &mut usize,
+    ) -> bool {
+        self.hit_moved(ray, rec, |ray, rec| {
+            self.ptr.hit_counting(ray, t_min, t_max, rec, visits)
+        })
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        if !self.ptr.bounding_box(output_box) {
+            return false;
+        }
+        *output_box = AABB::new(
+            output_box.min() + self.offset,
+            output_box.max() + self.offset,
+        );
+        true
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        self.ptr.materials(out);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    // the two coordinates spanning the plane of rotation, in right-handed order
+    fn plane(&self) -> (usize, usize) {
+        match self {
+            Axis::X => (1, 2),
+            Axis::Y => (2, 0),
+            Axis::Z => (0, 1),
+        }
+    }
+}
+
+pub struct Rotate {
+    ptr: Arc<dyn Hittable + Send + Sync>,
+    axis: Axis,
+    sin_theta: f64,
+    cos_theta: f64,
+    bbox: Option<AABB>,
+}
+
+impl Rotate {
+    pub fn new(ptr: Arc<dyn Hittable + Send + Sync>, axis: Axis, angle: f64) -> Rotate {
+        let radians = angle.to_radians();
+        let mut rotate = Rotate {
+            ptr,
+            axis,
+            sin_theta: radians.sin(),
+            cos_theta: radians.cos(),
+            bbox: None,
+        };
+
+        let mut inner = AABB::empty();
+        if rotate.ptr.bounding_box(&mut inner) {
+            rotate.bbox = Some(inner.transformed(|p| rotate.to_world(p)));
+        }
+        rotate
+    }
+
+    pub fn x(ptr: Arc<dyn Hittable + Send + Sync>, angle: f64) -> Rotate {
+        Rotate::new(ptr, Axis::X, angle)
+    }
+
+    pub fn y(ptr: Arc<dyn Hittable + Send + Sync>, angle: f64) -> Rotate {
+        Rotate::new(ptr, Axis::Y, angle)
+    }
+
+    pub fn z(ptr: Arc<dyn Hittable + Send + Sync>, angle: f64) -> Rotate {
+        Rotate::new(ptr, Axis::Z, angle)
+    }
+
+    fn rotate(&self, v: &Vec3, sin_theta: f64) -> Vec3 {
+        let (i, j) = self.axis.plane();
+        let mut out = [v.x(), v.y(), v.z()];
+        out[i] = self.cos_theta * v[i] - sin_theta * v[j];
+        out[j] = sin_theta * v[i] + self.cos_theta * v[j];
+        Vec3::new(out[0], out[1], out[2])
+    }
+
+    fn to_world(&self, v: &Vec3) -> Vec3 {
+        self.rotate(v, self.sin_theta)
+    }
+
+    fn to_object(&self, v: &Vec3) -> Vec3 {
+        self.rotate(v, -self.sin_theta)
+    }
+
+    // `hit` tests the rotated ray against the inner shape
+    fn hit_rotated<F>(&self, ray: &Ray, rec: &mut HitRecord, hit: F) -> bool
+    where
+        F: FnOnce(&Ray, &mut HitRecord) -> bool,
+    {
+        let rotated = Ray::new(self.to_object(ray.origin()), self.to_object(ray.dir()));
+        if !hit(&rotated, rec) {
+            return false;
+        }
+        // rotation preserves angles, so the inner front_face stays valid
+        rec.p = self.to_world(&rec.p);
+        rec.normal = self.to_world(&rec.normal);
+        true
+    }
+}
+
+impl Hittable for Rotate {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.hit_rotated(ray, rec, |ray, rec| self.ptr.hit(ray, t_min, t_max, rec))
+    }
+
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        visits: &mut usize,
+    ) -> bool {
+        self.hit_rotated(ray, rec, |ray, rec| {
+            self.ptr.hit_counting(ray, t_min, t_max, rec, visits)
+        })
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        match &self.bbox {
+            Some(bbox) => {
+                *output_box = bbox.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        self.ptr.materials(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::data::{Lambertian, Point3, Vec3};
+    use crate::engine::{BoxShape, HitRecord, Hittable, Ray, AABB};
+
+    use super::{Rotate, Translate};
+
+    fn cube(p0: Point3, p1: Point3) -> Arc<BoxShape> {
+        Arc::new(BoxShape::new(p0, p1, Lambertian::black_sh()))
+    }
+
+    #[test]
+    fn translate_moves_hits_and_bounds() {
+        let moved = Translate::new(
+            cube(Point3::zero(), Point3::new(1.0, 1.0, 1.0)),
+            Vec3::new(5.0, 0.0, 0.0),
+        );
+        let mut rec = HitRecord::empty();
+        let ray = Ray::new(Point3::new(5.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(moved.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.t, 1.0);
+        assert_eq!(rec.p, Point3::new(5.5, 0.5, 0.0));
+        let ray = Ray::new(Point3::new(0.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(!moved.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+
+        let mut bbox = AABB::empty();
+        assert!(moved.bounding_box(&mut bbox));
+        assert_eq!(bbox.min(), Point3::new(5.0, 0.0, 0.0));
+        assert_eq!(bbox.max(), Point3::new(6.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn rotate_turns_hits_normals_and_bounds() {
+        // a quarter turn about y takes x to -z and z to x
+        let turned = Rotate::y(cube(Point3::zero(), Point3::new(1.0, 2.0, 3.0)), 90.0);
+        let mut bbox = AABB::empty();
+        assert!(turned.bounding_box(&mut bbox));
+        assert_eq!(bbox.min(), Point3::new(0.0, 0.0, -1.0));
+        assert_eq!(bbox.max(), Point3::new(3.0, 2.0, 0.0));
+
+        let mut rec = HitRecord::empty();
+        let ray = Ray::new(Point3::new(1.5, 1.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(turned.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.t, 4.0);
+        assert_eq!(rec.p, Point3::new(1.5, 1.0, -1.0));
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, -1.0));
+        let ray = Ray::new(Point3::new(0.5, 1.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!turned.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+
+        // at 45 degrees the bounds grow to hold the corners
+        let turned = Rotate::y(
+            cube(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)),
+            45.0,
+        );
+        assert!(turned.bounding_box(&mut bbox));
+        let r = 2f64.sqrt();
+        assert_eq!(bbox.min(), Point3::new(-r, -1.0, -r));
+        assert_eq!(bbox.max(), Point3::new(r, 1.0, r));
+    }
+}
@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use crate::data::{Material, Point3, Vec3};
+use crate::engine::hittable::{HitRecord, Hittable};
+use crate::engine::triangle::{fill_record, intersect, triangle_box};
+
+use super::{Ray, AABB};
+
+const MAX_LEAF_SIZE: usize = 4;
+const MAX_DEPTH: usize = 64;
+
+// Interior nodes store their left child right after themselves and the index
+// of the right child in `first`; leaves cover triangles first..first + count.
+struct MeshNode {
+    bbox: AABB,
+    first: usize,
+    count: usize,
+}
+
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    indices: Vec<[usize; 3]>,
+    mat_ptr: Arc<dyn Material + Send + Sync>,
+    nodes: Vec<MeshNode>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        indices: Vec<[usize; 3]>,
+        mat_ptr: Arc<dyn Material + Send + Sync>,
+    ) -> TriangleMesh {
+        assert!(normals.is_empty() || normals.len() == positions.len());
+        assert!(uvs.is_empty() || uvs.len() == positions.len());
+        assert!(indices.iter().flatten().all(|&i| i < positions.len()));
+
+        let mut mesh = TriangleMesh {
+            positions,
+            normals,
+            uvs,
+            indices,
+            mat_ptr,
+            nodes: Vec::new(),
+        };
+        mesh.build();
+        mesh
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    fn vertices(&self, tri: usize) -> [Point3; 3] {
+        let [a, b, c] = self.indices[tri];
+        [self.positions[a], self.positions[b], self.positions[c]]
+    }
+
+    fn build(&mut self) {
+        if self.indices.is_empty() {
+            return;
+        }
+        let boxes: Vec<AABB> = (0..self.len())
+            .map(|tri| triangle_box(&self.vertices(tri)))
+            .collect();
+        let centroids: Vec<Point3> = boxes.iter().map(|b| 0.5 * (b.min() + b.max())).collect();
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        let mut nodes = Vec::with_capacity(2 * self.len() / MAX_LEAF_SIZE + 1);
+
+        build_node(&mut nodes, &boxes, &centroids, &mut order, 0, 1);
+
+        self.indices = order.iter().map(|&tri| self.indices[tri]).collect();
+        self.nodes = nodes;
+    }
+
+    // `visit` is called for every node whose box is tested
+    fn traverse<F>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        mut visit: F,
+    ) -> bool
+    where
+        F: FnMut(),
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut stack = [0; MAX_DEPTH + 2];
+        let mut stack_len = 1;
+        let mut closest = t_max;
+        let mut found = None;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let index = stack[stack_len];
+            let node = &self.nodes[index];
+            visit();
+            if !node.bbox.hit(ray, t_min, closest, rec) {
+                continue;
+            }
+            if node.count > 0 {
+                for tri in node.first..node.first + node.count {
+                    if let Some(hit) = intersect(&self.vertices(tri), ray, t_min, closest) {
+                        closest = hit.0;
+                        found = Some((tri, hit));
+                    }
+                }
+            } else {
+                stack[stack_len] = index + 1;
+                stack[stack_len + 1] = node.first;
+                stack_len += 2;
+            }
+        }
+
+        match found {
+            Some((tri, hit)) => {
+                let [a, b, c] = self.indices[tri];
+                let normals = if self.normals.is_empty() {
+                    None
+                } else {
+                    Some([self.normals[a], self.normals[b], self.normals[c]])
+                };
+                let uvs = if self.uvs.is_empty() {
+                    None
+                } else {
+                    Some([self.uvs[a], self.uvs[b], self.uvs[c]])
+                };
+                fill_record(
+                    rec,
+                    ray,
+                    hit,
+                    &self.vertices(tri),
+                    normals.as_ref(),
+                    uvs.as_ref(),
+                );
+                rec.mat_ptr = Arc::clone(&self.mat_ptr);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn build_node(
+    nodes: &mut Vec<MeshNode>,
+    boxes: &[AABB],
+    centroids: &[Point3],
+    order: &mut [usize],
+    first: usize,
+    depth: usize,
+) -> usize {
+    let index = nodes.len();
+    let bbox = order[1..]
+        .iter()
+        .fold(boxes[order[0]].clone(), |acc, &tri| {
+            AABB::surrounding_box(&acc, &boxes[tri])
+        });
+
+    if order.len() <= MAX_LEAF_SIZE || depth >= MAX_DEPTH {
+        nodes.push(MeshNode {
+            bbox,
+            first,
+            count: order.len(),
+        });
+        return index;
+    }
+
+    let first_centroid = centroids[order[0]];
+    let centroid_box = order
+        .iter()
+        .fold(AABB::new(first_centroid, first_centroid), |acc, &tri| {
+            AABB::surrounding_box(&acc, &AABB::new(centroids[tri], centroids[tri]))
+        });
+    let extent = centroid_box.max() - centroid_box.min();
+    let axis = if extent.x() > extent.y() && extent.x() > extent.z() {
+        0
+    } else if extent.y() > extent.z() {
+        1
+    } else {
+        2
+    };
+
+    order.sort_by(|&a, &b| centroids[a][axis].partial_cmp(&centroids[b][axis]).unwrap());
+    let mid = order.len() / 2;
+
+    nodes.push(MeshNode {
+        bbox,
+        first: 0,
+        count: 0,
+    });
+    let (left, right) = order.split_at_mut(mid);
+    build_node(nodes, boxes, centroids, left, first, depth + 1);
+    let right_index = build_node(nodes, boxes, centroids, right, first + mid, depth + 1);
+    nodes[index].first = right_index;
+    index
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.traverse(ray, t_min, t_max, rec, || {})
+    }
+
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        visits: &mut usize,
+    ) -> bool {
+        self.traverse(ray, t_min, t_max, rec, || *visits += 1)
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        match self.nodes.first() {
+            Some(root) => {
+                *output_box = root.bbox.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        out.push(Arc::clone(&self.mat_ptr));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::data::{Lambertian, Point3, Vec3};
+    use crate::engine::{HitRecord, Hittable, HittableList, Ray, Triangle};
+    use crate::util::rng::seeded_rng;
+
+    use super::TriangleMesh;
+
+    #[test]
+    fn matches_brute_force() {
+        let mat = Lambertian::black_sh();
+        let rng = &mut seeded_rng(7);
+        let positions: Vec<Point3> = (0..300).map(|_| Vec3::rand_range(-5.0, 5.0, rng)).collect();
+        let indices: Vec<[usize; 3]> = (0..100).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect();
+
+        let mut list = HittableList::new();
+        for tri in indices.iter() {
+            let vertices = [positions[tri[0]], positions[tri[1]], positions[tri[2]]];
+            list.add(Arc::new(Triangle::new(vertices, mat.clone())));
+        }
+        let mesh = TriangleMesh::new(positions, Vec::new(), Vec::new(), indices, mat);
+
+        for _ in 0..1000 {
+            let ray = Ray::new(
+                Vec3::rand_range(-10.0, 10.0, rng),
+                Vec3::rand_range(-1.0, 1.0, rng),
+            );
+            let mut expected = HitRecord::empty();
+            let mut actual = HitRecord::empty();
+            let hit_list = list.hit(&ray, 0.001, f64::INFINITY, &mut expected);
+            let hit_mesh = mesh.hit(&ray, 0.001, f64::INFINITY, &mut actual);
+            assert_eq!(hit_list, hit_mesh);
+            if hit_list {
+                assert_eq!(expected.p, actual.p);
+                assert_eq!(expected.normal, actual.normal);
+            }
+        }
+    }
+}
@@ -1,15 +1,31 @@
 pub mod aabb;
+pub mod aarect;
+pub mod background;
+pub mod box_shape;
 pub mod bvh;
 pub mod camera;
 pub mod hittable;
 pub mod hittable_list;
+pub mod instance;
+pub mod mesh;
+pub mod pdf;
 pub mod ray;
 pub mod sphere;
+pub mod transform;
+pub mod triangle;
 
 pub use aabb::AABB;
+pub use aarect::{XYRect, XZRect, YZRect};
+pub use background::Background;
+pub use box_shape::BoxShape;
 pub use bvh::BVHnode;
-pub use camera::Camera;
+pub use camera::{Camera, CameraConfig};
 pub use hittable::{HitRecord, Hittable};
 pub use hittable_list::HittableList;
+pub use instance::{Axis, Rotate, Translate};
+pub use mesh::TriangleMesh;
+pub use pdf::{CosinePdf, HittablePdf, MixturePdf, Pdf, SpherePdf};
 pub use ray::Ray;
 pub use sphere::Sphere;
+pub use transform::Transform;
+pub use triangle::Triangle;
@@ -0,0 +1,146 @@
+use std::f64::consts::PI;
+
+use crate::data::{Onb, Point3, Vec3};
+use crate::util::sampler::{sample_cosine_hemisphere, sample_sphere};
+
+use super::Hittable;
+
+// A distribution of directions to scatter or look for light in, and its
+// density per unit solid angle.
+pub trait Pdf {
+    fn value(&self, direction: &Vec3) -> f64;
+    fn generate(&self, u: (f64, f64)) -> Vec3;
+}
+
+// proportional to the cosine with `normal`, zero below the surface
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: &Vec3) -> CosinePdf {
+        CosinePdf {
+            uvw: Onb::from_w(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine = direction.unit().dot(&self.uvw.w());
+        cosine.max(0.0) / PI
+    }
+
+    fn generate(&self, u: (f64, f64)) -> Vec3 {
+        self.uvw.local(&sample_cosine_hemisphere(u))
+    }
+}
+
+// every direction equally likely
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn generate(&self, u: (f64, f64)) -> Vec3 {
+        sample_sphere(u)
+    }
+}
+
+// towards points on `objects` as seen from `origin`
+pub struct HittablePdf<'a> {
+    objects: &'a dyn Hittable,
+    origin: Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(objects: &'a dyn Hittable, origin: Point3) -> HittablePdf<'a> {
+        HittablePdf { objects, origin }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.objects.pdf_value(&self.origin, direction)
+    }
+
+    fn generate(&self, u: (f64, f64)) -> Vec3 {
+        self.objects.random(&self.origin, u)
+    }
+}
+
+// an even mix of two distributions
+pub struct MixturePdf<'a> {
+    pdfs: [&'a dyn Pdf; 2],
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(a: &'a dyn Pdf, b: &'a dyn Pdf) -> MixturePdf<'a> {
+        MixturePdf { pdfs: [a, b] }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        0.5 * self.pdfs[0].value(direction) + 0.5 * self.pdfs[1].value(direction)
+    }
+
+    // the first coordinate picks the distribution and is then reused
+    fn generate(&self, u: (f64, f64)) -> Vec3 {
+        if u.0 < 0.5 {
+            self.pdfs[0].generate((2.0 * u.0, u.1))
+        } else {
+            self.pdfs[1].generate((2.0 * u.0 - 1.0, u.1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::Rng;
+
+    use crate::data::{Lambertian, Point3, Vec3};
+    use crate::engine::{Sphere, XZRect};
+    use crate::util::rng::seeded_rng;
+    use crate::util::sampler::sample_sphere;
+
+    use super::{CosinePdf, HittablePdf, MixturePdf, Pdf, SpherePdf};
+
+    #[test]
+    fn densities_integrate_to_one_over_what_they_generate() {
+        let material = Arc::new(Lambertian::from_rgb(0.5, 0.5, 0.5));
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 2.0), 1.0, material.clone());
+        let rect = XZRect::new(-1.0, 2.0, -1.0, 1.0, 1.5, material);
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        let cosine = CosinePdf::new(&Vec3::new(1.0, 1.0, 0.0));
+        let towards_sphere = HittablePdf::new(&sphere, origin);
+        let towards_rect = HittablePdf::new(&rect, origin);
+        let mixture = MixturePdf::new(&cosine, &towards_sphere);
+        let pdfs: [&dyn Pdf; 5] = [
+            &cosine,
+            &SpherePdf,
+            &towards_sphere,
+            &towards_rect,
+            &mixture,
+        ];
+
+        let rng = &mut seeded_rng(9);
+        for pdf in pdfs.iter() {
+            let n = 100_000;
+            let integral = (0..n)
+                .map(|_| pdf.value(&sample_sphere((rng.gen(), rng.gen()))))
+                .sum::<f64>()
+                * 4.0
+                * std::f64::consts::PI
+                / n as f64;
+            assert!((integral - 1.0).abs() < 0.05, "{}", integral);
+            for _ in 0..100 {
+                assert!(pdf.value(&pdf.generate((rng.gen(), rng.gen()))) > 0.0);
+            }
+        }
+    }
+}
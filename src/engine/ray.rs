@@ -18,6 +18,6 @@ impl Ray {
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
-        return self.orig + self.dir * t;
+        self.orig + self.dir * t
     }
 }
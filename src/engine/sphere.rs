@@ -2,10 +2,11 @@ use std::f64::consts::PI;
 use std::sync::Arc;
 
 use crate::data::vec3::Vec3;
-use crate::data::{Lambertian, Material, Point3};
+use crate::data::{Material, Onb, Point3};
 use crate::engine::hittable::{HitRecord, Hittable};
+use crate::util::sampler::{sample_cone, sample_sphere};
 
-use super::AABB;
+use super::{Ray, AABB};
 
 pub struct Sphere {
     center: Vec3,
@@ -29,6 +30,17 @@ impl Sphere {
         *u = phi / (2.0 * PI);
         *v = theta / PI;
     }
+
+    // cosine of the half angle of the cone the sphere fills seen from
+    // `origin`, None from inside
+    fn cos_max(&self, origin: &Point3) -> Option<f64> {
+        let ratio = self.radius * self.radius / (self.center - *origin).len_sq();
+        if ratio >= 1.0 {
+            None
+        } else {
+            Some((1.0 - ratio).sqrt())
+        }
+    }
 }
 
 impl Hittable for Sphere {
@@ -56,7 +68,7 @@ impl Hittable for Sphere {
         rec.set_face_normal(ray, &outward_normal);
         Sphere::get_uv(&outward_normal, &mut rec.u, &mut rec.v);
         rec.mat_ptr = Arc::clone(&self.mat_ptr);
-        return true;
+        true
     }
 
     fn bounding_box(&self, output_box: &mut super::AABB) -> bool {
@@ -66,4 +78,33 @@ impl Hittable for Sphere {
         );
         true
     }
+
+    // Samples the cone of directions the sphere covers, or every direction
+    // from inside it.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let mut rec = HitRecord::empty();
+        if !self.hit(
+            &Ray::new(*origin, *direction),
+            0.001,
+            f64::INFINITY,
+            &mut rec,
+        ) {
+            return 0.0;
+        }
+        match self.cos_max(origin) {
+            Some(cos_max) => 1.0 / (2.0 * PI * (1.0 - cos_max)),
+            None => 1.0 / (4.0 * PI),
+        }
+    }
+
+    fn random(&self, origin: &Point3, u: (f64, f64)) -> Vec3 {
+        match self.cos_max(origin) {
+            Some(cos_max) => Onb::from_w(&(self.center - *origin)).local(&sample_cone(u, cos_max)),
+            None => sample_sphere(u),
+        }
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        out.push(Arc::clone(&self.mat_ptr));
+    }
 }
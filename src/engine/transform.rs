@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use crate::data::{Mat4, Material};
+use crate::engine::hittable::{HitRecord, Hittable};
+
+use super::{Ray, AABB};
+
+pub struct Transform {
+    ptr: Arc<dyn Hittable + Send + Sync>,
+    matrix: Mat4,
+    inverse: Mat4,
+    normal_matrix: Mat4,
+    bbox: Option<AABB>,
+}
+
+impl Transform {
+    pub fn new(ptr: Arc<dyn Hittable + Send + Sync>, matrix: Mat4) -> Option<Transform> {
+        let inverse = matrix.inverse()?;
+        let mut inner = AABB::empty();
+        let bbox = if ptr.bounding_box(&mut inner) {
+            Some(inner.transformed(|p| matrix.transform_point(p)))
+        } else {
+            None
+        };
+
+        Some(Transform {
+            ptr,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            bbox,
+        })
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.matrix
+    }
+
+    // `hit` tests the ray in object space against the inner shape
+    fn hit_local<F>(&self, ray: &Ray, rec: &mut HitRecord, hit: F) -> bool
+    where
+        F: FnOnce(&Ray, &mut HitRecord) -> bool,
+    {
+        // the direction is left unnormalized so t is the same in both spaces
+        let local = Ray::new(
+            self.inverse.transform_point(ray.origin()),
+            self.inverse.transform_vector(ray.dir()),
+        );
+        if !hit(&local, rec) {
+            return false;
+        }
+        rec.p = self.matrix.transform_point(&rec.p);
+        rec.normal = self.normal_matrix.transform_vector(&rec.normal).unit();
+        true
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.hit_local(ray, rec, |ray, rec| self.ptr.hit(ray, t_min, t_max, rec))
+    }
+
+    fn hit_counting(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        rec: &mut HitRecord,
+        visits: &mut usize,
+    ) -> bool {
+        self.hit_local(ray, rec, |ray, rec| {
+            self.ptr.hit_counting(ray, t_min, t_max, rec, visits)
+        })
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        match &self.bbox {
+            Some(bbox) => {
+                *output_box = bbox.clone();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        self.ptr.materials(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::data::{Lambertian, Mat4, Point3, Vec3};
+    use crate::engine::{HitRecord, Hittable, Ray, Sphere, AABB};
+
+    use super::Transform;
+
+    #[test]
+    fn transform_maps_hits_normals_and_bounds() {
+        // a unit sphere stretched to twice its width and moved 3 along x
+        let sphere = Arc::new(Sphere::new(Point3::zero(), 1.0, Lambertian::black_sh()));
+        let matrix =
+            Mat4::translation(&Vec3::new(3.0, 0.0, 0.0)) * Mat4::scaling(&Vec3::new(2.0, 1.0, 1.0));
+        let ellipsoid = Transform::new(sphere, matrix).unwrap();
+
+        let mut rec = HitRecord::empty();
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(ellipsoid.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        assert_eq!(rec.t, 6.0);
+        assert_eq!(rec.p, Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(rec.normal, Vec3::new(-1.0, 0.0, 0.0));
+
+        // off the axis the normal follows the stretched surface, not the ray
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(ellipsoid.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+        let x = -2.0 * 0.75f64.sqrt();
+        assert_eq!(rec.p, Point3::new(3.0 + x, 0.5, 0.0));
+        assert_eq!(rec.normal, Vec3::new(x / 4.0, 0.5, 0.0).unit());
+
+        let ray = Ray::new(Point3::new(-5.0, 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!ellipsoid.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+
+        let mut bbox = AABB::empty();
+        assert!(ellipsoid.bounding_box(&mut bbox));
+        assert_eq!(bbox.min(), Point3::new(1.0, -1.0, -1.0));
+        assert_eq!(bbox.max(), Point3::new(5.0, 1.0, 1.0));
+        assert!(Transform::new(Arc::new(bbox), Mat4::scaling(&Vec3::zero())).is_none());
+    }
+}
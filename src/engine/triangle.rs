@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::data::{Material, Point3, Vec3};
+use crate::engine::hittable::{HitRecord, Hittable};
+
+use super::{Ray, AABB};
+
+const EPSILON: f64 = 1e-9;
+const PADDING: f64 = 0.0001;
+
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    mat_ptr: Arc<dyn Material + Send + Sync>,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Point3; 3], mat_ptr: Arc<dyn Material + Send + Sync>) -> Triangle {
+        Triangle {
+            vertices,
+            normals: None,
+            uvs: None,
+            mat_ptr,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Triangle {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Triangle {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        match intersect(&self.vertices, ray, t_min, t_max) {
+            Some((t, b1, b2)) => {
+                fill_record(
+                    rec,
+                    ray,
+                    (t, b1, b2),
+                    &self.vertices,
+                    self.normals.as_ref(),
+                    self.uvs.as_ref(),
+                );
+                rec.mat_ptr = Arc::clone(&self.mat_ptr);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn bounding_box(&self, output_box: &mut AABB) -> bool {
+        *output_box = triangle_box(&self.vertices);
+        true
+    }
+
+    fn materials(&self, out: &mut Vec<Arc<dyn Material + Send + Sync>>) {
+        out.push(Arc::clone(&self.mat_ptr));
+    }
+}
+
+// Möller–Trumbore, returns t and the barycentric coordinates of v1 and v2
+pub(super) fn intersect(
+    v: &[Point3; 3],
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = v[1] - v[0];
+    let edge2 = v[2] - v[0];
+    let pvec = ray.dir().cross(&edge2);
+    let det = edge1.dot(&pvec);
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = *ray.origin() - v[0];
+    let b1 = tvec.dot(&pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = tvec.cross(&edge1);
+    let b2 = ray.dir().dot(&qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&qvec) * inv_det;
+    if !(t_min..=t_max).contains(&t) {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+pub(super) fn fill_record(
+    rec: &mut HitRecord,
+    ray: &Ray,
+    (t, b1, b2): (f64, f64, f64),
+    v: &[Point3; 3],
+    normals: Option<&[Vec3; 3]>,
+    uvs: Option<&[(f64, f64); 3]>,
+) {
+    let b0 = 1.0 - b1 - b2;
+    rec.t = t;
+    rec.p = ray.at(t);
+
+    let geometric = (v[1] - v[0]).cross(&(v[2] - v[0])).unit();
+    rec.set_face_normal(ray, &geometric);
+    if let Some(n) = normals {
+        let shading = (b0 * n[0] + b1 * n[1] + b2 * n[2]).unit();
+        rec.normal = if rec.front_face {
+            shading
+        } else {
+            -1.0 * shading
+        };
+    }
+
+    match uvs {
+        Some(uv) => {
+            rec.u = b0 * uv[0].0 + b1 * uv[1].0 + b2 * uv[2].0;
+            rec.v = b0 * uv[0].1 + b1 * uv[1].1 + b2 * uv[2].1;
+        }
+        None => {
+            rec.u = b1;
+            rec.v = b2;
+        }
+    }
+}
+
+pub(super) fn triangle_box(v: &[Point3; 3]) -> AABB {
+    let mut min = [0.0; 3];
+    let mut max = [0.0; 3];
+    for axis in 0..3 {
+        min[axis] = f64::min(v[0][axis], f64::min(v[1][axis], v[2][axis]));
+        max[axis] = f64::max(v[0][axis], f64::max(v[1][axis], v[2][axis]));
+        if max[axis] - min[axis] < PADDING {
+            min[axis] -= PADDING;
+            max[axis] += PADDING;
+        }
+    }
+    AABB::new(
+        Point3::new(min[0], min[1], min[2]),
+        Point3::new(max[0], max[1], max[2]),
+    )
+}
@@ -0,0 +1,4 @@
+pub mod data;
+pub mod engine;
+pub mod render;
+pub mod util;
@@ -1,108 +1,309 @@
-mod data;
-mod engine;
-mod util;
+mod cli;
 
-use data::{Color, Point3, Vec3, worlds::{balls_perlin, marble_land, world_map}};
-use engine::{Camera, HitRecord, Hittable, Ray};
-use util::thread_pool::{PlacedPixel, RTThreadPool};
+use cli::{Command, Options};
+use rust_tracer::data::{
+    scene::{load_scene, Scene},
+    worlds,
+};
+use rust_tracer::render::{
+    heatmap, load_checkpoint, save_checkpoint, save_image, save_with_aovs, Accumulator,
+    AdaptiveSampling, Denoiser, Filter, Framebuffer, ImageFormat, IntegratorKind, OutputError,
+    PostProcess, Progress, RenderSettings, Renderer, SceneId,
+};
+use rust_tracer::util::rng::random_seed;
 
-use std::sync::{Arc, Mutex};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
-use lodepng;
-use rand::Rng;
-
-const ASPECT_RATIO: f64 = 16.0 / 9.0;
-const WIDTH: usize = 800;
-const HEIGHT: usize = (WIDTH as f64 / ASPECT_RATIO) as usize;
-const SAMPLES_PER_PIXEL: usize = 50;
-const MAX_DEPTH: usize = 100;
-const N_THREADS: usize = 10;
-
-fn ray_color(
-    r: &Ray,
-    world: Arc<dyn Hittable>,
-    rng: &mut rand::rngs::StdRng,
-    depth: usize,
-) -> Vec3 {
-    let mut rec = HitRecord::empty();
+fn find_scene(name: &str, seed: u64) -> Result<(Scene, SceneId), String> {
+    if let Some(scene) = worlds::builtin(name, seed) {
+        return Ok((scene, SceneId::new(name, name.as_bytes())));
+    }
+    let path = Path::new(name);
+    if !path.is_file() {
+        return Err(format!(
+            "'{}' is neither a built-in scene ({}) nor a scene file",
+            name,
+            worlds::BUILTIN_NAMES.join(", ")
+        ));
+    }
+    let source = fs::read(path).map_err(|e| format!("cannot read {}: {}", name, e))?;
+    let scene = load_scene(path).map_err(|e| e.to_string())?;
+    Ok((scene, SceneId::new(name, &source)))
+}
 
-    if depth <= 0 {
-        return Color::new(0.0, 0.0, 0.0);
+fn image_format(path: &Path, options: &Options) -> ImageFormat {
+    match ImageFormat::from_path(path) {
+        Some(ImageFormat::Exr { .. }) => ImageFormat::Exr { half: options.half },
+        Some(format) => format,
+        None => {
+            eprintln!("error: {}", OutputError::UnknownFormat(path.to_path_buf()));
+            process::exit(2);
+        }
     }
+}
 
-    if world.hit(r, 0.001, f64::INFINITY, &mut rec) {
-        let mut attenuation = Color::new(1.0, 1.0, 1.0);
-        let mut scattered = Ray::new(Vec3::zero(), Vec3::zero());
-        if rec
-            .mat_ptr
-            .scatter(r, &rec, &mut attenuation, &mut scattered)
-        {
-            return attenuation * ray_color(&scattered, world, rng, depth - 1);
+fn write_image(
+    path: &Path,
+    framebuffer: &Framebuffer,
+    format: ImageFormat,
+    post: &PostProcess,
+) -> Result<(), String> {
+    save_image(path, framebuffer, format, post)
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
+
+fn apply_options(settings: &mut RenderSettings, options: &Options, seed: u64) {
+    let aspect_ratio = settings.aspect_ratio();
+    match (options.width, options.height) {
+        (Some(width), Some(height)) => {
+            settings.width = width;
+            settings.height = height;
+        }
+        (Some(width), None) => {
+            settings.width = width;
+            settings.height = ((width as f64 / aspect_ratio) as usize).max(1);
         }
-        return Color::zero();
+        (None, Some(height)) => {
+            settings.width = ((height as f64 * aspect_ratio) as usize).max(1);
+            settings.height = height;
+        }
+        (None, None) => {}
+    }
+    if let Some(samples_per_pixel) = options.samples_per_pixel {
+        settings.samples_per_pixel = samples_per_pixel;
+    }
+    if let Some(max_depth) = options.max_depth {
+        settings.max_depth = max_depth;
     }
-    let unit_dir = r.dir().unit();
-    let t = 0.5 * (unit_dir.y() + 1.0);
-    (1.0 - t) * Vec3::new(1.0, 1.0, 1.0) + t * Vec3::new(0.5, 0.7, 1.0)
+    if let Some(integrator) = options.integrator {
+        settings.integrator = integrator;
+    }
+    if let (IntegratorKind::AmbientOcclusion { radius }, Some(ao_radius)) =
+        (&mut settings.integrator, options.ao_radius)
+    {
+        *radius = ao_radius;
+    }
+    if let Some(threshold) = options.adaptive {
+        let adaptive = settings
+            .adaptive
+            .get_or_insert_with(AdaptiveSampling::default);
+        adaptive.threshold = threshold;
+    }
+    if let (Some(adaptive), Some(min_samples)) = (&mut settings.adaptive, options.min_samples) {
+        adaptive.min_samples = min_samples;
+    }
+    settings.seed = Some(seed);
+    if let Some(sampler) = options.sampler {
+        settings.sampler = sampler;
+    }
+    if let Some(kind) = options.filter {
+        settings.filter = Filter::new(kind);
+    }
+    if let Some(radius) = options.filter_radius {
+        settings.filter.radius = radius;
+    }
+    if let Some(tile_size) = options.tile_size {
+        settings.tile_size = tile_size;
+    }
+    if let Some(tile_order) = options.tile_order {
+        settings.tile_order = tile_order;
+    }
+    settings.progressive = options.progressive;
 }
 
-fn generate_image() -> Vec<[u8; 4]> {
-    let world = world_map();
+fn main() {
+    let options = match cli::parse_args(env::args().skip(1)) {
+        Ok(Command::Render(options)) => *options,
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            process::exit(2);
+        }
+    };
 
-    let mut pool = RTThreadPool::new(N_THREADS, WIDTH, HEIGHT);
-    pool.start_collecting();
+    let output_format = image_format(&options.output, &options);
+    let preview_format = options
+        .preview
+        .as_ref()
+        .map(|path| image_format(path, &options));
+    let heatmap_format = options
+        .heatmap
+        .as_ref()
+        .map(|path| image_format(path, &options));
 
-    let look_from = Point3::new(13.0, 2.0, 3.0);
-    let look_at = Point3::new(0.0, 0.0, 0.0);
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-    let dist_to_focus = 10.0;
-    let aperture = 0.0;
+    let checkpoint = options.resume.as_ref().map(|path| {
+        load_checkpoint(path).unwrap_or_else(|e| {
+            eprintln!("error: cannot resume from {}: {}", path.display(), e);
+            process::exit(1);
+        })
+    });
+    let seed = match &checkpoint {
+        Some(checkpoint) => checkpoint.settings.seed.unwrap(),
+        None => options.seed.unwrap_or_else(|| {
+            let seed = random_seed();
+            println!("Using seed {}", seed);
+            seed
+        }),
+    };
+    let name = match (&options.scene, &checkpoint) {
+        (Some(name), _) => name.as_str(),
+        (None, Some(checkpoint)) => checkpoint.scene.name.as_str(),
+        (None, None) => "marble_land",
+    };
+    let (mut scene, scene_id) = match find_scene(name, seed) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
 
-    let camera = Camera::new(
-        look_from,
-        look_at,
-        vup,
-        ASPECT_RATIO,
-        20.0,
-        aperture,
-        dist_to_focus,
-    );
-    let camera = Arc::new(camera);
+    let mut accumulator = match checkpoint {
+        Some(checkpoint) => {
+            if let Err(e) = checkpoint.check(&scene_id, &scene.camera) {
+                let path = options.resume.as_ref().unwrap();
+                eprintln!("error: cannot resume from {}: {}", path.display(), e);
+                process::exit(1);
+            }
+            scene.settings = RenderSettings {
+                post: scene.settings.post,
+                ..checkpoint.settings
+            };
+            if let Some(samples_per_pixel) = options.samples_per_pixel {
+                scene.settings.samples_per_pixel = samples_per_pixel;
+            }
+            checkpoint.accumulator
+        }
+        None => {
+            apply_options(&mut scene.settings, &options, seed);
+            Accumulator::new(scene.settings.width, scene.settings.height)
+        }
+    };
+    scene.settings.threads = options.threads;
+    let post = &mut scene.settings.post;
+    post.tone_map = options.tone_map.unwrap_or(post.tone_map);
+    post.exposure = options.exposure.unwrap_or(post.exposure);
+    post.dither |= options.dither;
+
+    let camera = scene.build_camera();
+    let renderer = Renderer::new(scene.settings.clone()).with_lights(scene.lights.clone());
+    let checkpoint_path = options.checkpoint.as_ref().or(options.resume.as_ref());
+    if checkpoint_path.is_some() {
+        let cancel = renderer.cancel_flag();
+        let handler = ctrlc::set_handler(move || {
+            eprintln!("\nInterrupted, finishing tiles in progress");
+            cancel.store(true, Ordering::SeqCst);
+        });
+        if let Err(e) = handler {
+            eprintln!("warning: cannot catch Ctrl-C: {}", e);
+        }
+    }
 
-    for j in 0..HEIGHT {
-        for i in 0..WIDTH {
-            let world = Arc::clone(&world);
-            let camera = Arc::clone(&camera);
-            let process_pixel = move |rng: Arc<Mutex<rand::rngs::StdRng>>| {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 1..=SAMPLES_PER_PIXEL {
-                    let mut rng = rng.lock().unwrap();
-                    let r1: f64 = rng.gen();
-                    let r2: f64 = rng.gen();
-                    let u = (i as f64 + r1) / (WIDTH - 1) as f64;
-                    let v = (j as f64 + r2) / (HEIGHT - 1) as f64;
-                    let ray = camera.get_ray(u, v);
-                    pixel_color += ray_color(&ray, world.clone(), &mut rng, MAX_DEPTH);
+    let preview_every = Duration::from_secs_f64(options.preview_seconds);
+    let checkpoint_every = Duration::from_secs_f64(options.checkpoint_seconds);
+    let mut last_preview = (Instant::now(), 0);
+    let mut last_checkpoint = Instant::now();
+    let save = |path: &Path, accumulator: &Accumulator| match save_checkpoint(
+        path,
+        &scene_id,
+        &scene.camera,
+        renderer.settings(),
+        accumulator,
+    ) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("warning: cannot write checkpoint {}: {}", path.display(), e);
+            false
+        }
+    };
+    let finished = renderer.render_into(
+        scene.world.clone(),
+        &camera,
+        &mut accumulator,
+        |accumulator, progress| {
+            if let Some(path) = checkpoint_path {
+                if last_checkpoint.elapsed() >= checkpoint_every {
+                    save(path, accumulator);
+                    last_checkpoint = Instant::now();
                 }
-                Ok(PlacedPixel {
-                    i,
-                    j,
-                    color: pixel_color.as_color(SAMPLES_PER_PIXEL),
-                })
+            }
+            let (path, format) = match (&options.preview, preview_format, progress) {
+                (Some(path), Some(format), Progress::Pass) => (path, format),
+                _ => return,
             };
-            pool.execute(process_pixel);
+            let (time, passes) = &mut last_preview;
+            *passes += 1;
+            let due_passes = options.preview_passes.is_some_and(|n| *passes >= n);
+            if time.elapsed() < preview_every && !due_passes {
+                return;
+            }
+            if let Err(e) = write_image(
+                path,
+                &accumulator.resolve(),
+                format,
+                &renderer.settings().post,
+            ) {
+                eprintln!("warning: {}", e);
+            }
+            last_preview = (Instant::now(), 0);
+        },
+    );
+
+    if !finished {
+        if let Some(path) = checkpoint_path {
+            if save(path, &accumulator) {
+                println!(
+                    "Progress saved to {}, continue with --resume",
+                    path.display()
+                );
+            }
         }
+        process::exit(130);
     }
-    pool.collect();
-    let end_image = pool.end_image.lock().unwrap().clone();
-    end_image
-}
 
-fn main() {
-    // let mut buffer: [[u8; 4]; WIDTH * HEIGHT] = [[0, 0, 0, 255]; WIDTH * HEIGHT];
-    let buffer = generate_image();
-    match lodepng::encode32_file("out.png", buffer.as_ref(), WIDTH, HEIGHT) {
-        Ok(_) => println!("Image saved..."),
-        Err(_) => println!("Error"),
+    if let (Some(path), Some(format)) = (&options.heatmap, heatmap_format) {
+        let samples_per_pixel = renderer.settings().samples_per_pixel;
+        let image = heatmap(&accumulator, samples_per_pixel);
+        if let Err(e) = write_image(path, &image, format, &PostProcess::default()) {
+            eprintln!("warning: {}", e);
+        }
+    }
+
+    let mut framebuffer = accumulator.resolve();
+    let aovs = if options.denoise || !options.aovs.is_empty() {
+        Some(renderer.render_aovs(scene.world.clone(), &camera))
+    } else {
+        None
+    };
+    if let (true, Some(aovs)) = (options.denoise, &aovs) {
+        framebuffer = Denoiser::default().denoise(&framebuffer, &accumulator.variance(), aovs);
+    }
+    let post = &renderer.settings().post;
+    let saved = match &aovs {
+        Some(aovs) if !options.aovs.is_empty() => save_with_aovs(
+            &options.output,
+            &framebuffer,
+            aovs,
+            &options.aovs,
+            output_format,
+            post,
+        )
+        .map_err(|e| format!("cannot write {}: {}", options.output.display(), e)),
+        _ => write_image(&options.output, &framebuffer, output_format, post),
+    };
+    match saved {
+        Ok(_) => println!("Image saved to {}", options.output.display()),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
     };
 }
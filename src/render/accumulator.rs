@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+use crate::data::Color;
+
+use super::tile::{FilmTile, Tile, TileBuffer};
+use super::Framebuffer;
+
+// Running sum of every sample taken so far, the sum of their squared
+// luminance and how many samples each pixel has, plus the filtered film of
+// every tile rendered so far. Each pass adds its samples on top of the
+// previous sums, so the final image does not depend on how the samples were
+// split into passes or runs.
+pub struct Accumulator {
+    sum: Framebuffer,
+    squares: Vec<f64>,
+    samples: Vec<usize>,
+    film: BTreeMap<(usize, usize), FilmTile>,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Accumulator {
+        Accumulator {
+            sum: Framebuffer::new(width, height),
+            squares: vec![0.0; width * height],
+            samples: vec![0; width * height],
+            film: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_parts(
+        sum: Framebuffer,
+        squares: Vec<f64>,
+        samples: Vec<usize>,
+        film: Vec<FilmTile>,
+    ) -> Accumulator {
+        assert_eq!(sum.pixels().len(), squares.len());
+        assert_eq!(sum.pixels().len(), samples.len());
+        Accumulator {
+            sum,
+            squares,
+            samples,
+            film: film
+                .into_iter()
+                .map(|film| ((film.tile.y, film.tile.x), film))
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.sum.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.sum.height()
+    }
+
+    pub fn sum(&self) -> &Framebuffer {
+        &self.sum
+    }
+
+    pub fn squares(&self) -> &[f64] {
+        &self.squares
+    }
+
+    pub fn samples(&self) -> &[usize] {
+        &self.samples
+    }
+
+    // film tiles in the order they are resolved in
+    pub fn film(&self) -> impl Iterator<Item = &FilmTile> {
+        self.film.values()
+    }
+
+    // A buffer that continues the tile up to `end` samples per pixel, with
+    // a film reaching `margin` pixels past it.
+    pub fn tile(&self, tile: Tile, end: usize, margin: usize) -> TileBuffer {
+        let mut buffer = self.sum.read_tile(tile);
+        buffer.squares = tile.read(&self.squares, self.width());
+        buffer.counts = tile.read(&self.samples, self.width());
+        buffer.targets = buffer.counts.iter().map(|&count| count.max(end)).collect();
+        buffer.film = match self.film.get(&(tile.y, tile.x)) {
+            Some(film) => film.clone(),
+            None => FilmTile::new(tile, tile.expand(margin, self.width(), self.height())),
+        };
+        buffer
+    }
+
+    // returns how many samples the buffer added
+    pub fn write_tile(&mut self, buffer: TileBuffer) -> usize {
+        let (tile, width) = (buffer.tile, self.width());
+        let before: usize = tile.read(&self.samples, width).iter().sum();
+        self.sum.write_tile(&buffer);
+        tile.write(&mut self.squares, width, &buffer.squares);
+        tile.write(&mut self.samples, width, &buffer.counts);
+        let added = buffer.counts.iter().sum::<usize>() - before;
+        self.film.insert((tile.y, tile.x), buffer.film);
+        added
+    }
+
+    // Adds up the film of every tile, always in the same order so splats
+    // that cross tile borders sum to the same bits however the tiles were
+    // scheduled, and normalizes by the filter weights. Pixels without any
+    // weight fall back to the plain mean of their samples.
+    pub fn resolve(&self) -> Framebuffer {
+        let width = self.width();
+        let mut weighted = vec![Color::zero(); self.samples.len()];
+        let mut weights = vec![0.0; self.samples.len()];
+        for film in self.film.values() {
+            let area = film.area;
+            for (k, (&color, &weight)) in film.weighted.iter().zip(&film.weights).enumerate() {
+                let p = (area.y + k / area.width) * width + area.x + k % area.width;
+                weighted[p] += color;
+                weights[p] += weight;
+            }
+        }
+        let pixels = self
+            .sum
+            .pixels()
+            .iter()
+            .zip(self.samples.iter())
+            .zip(weighted.iter().zip(weights.iter()))
+            .map(|((&sum, &count), (&weighted, &weight))| {
+                if weight.abs() > 1e-12 {
+                    weighted / weight
+                } else {
+                    sum / count.max(1) as f64
+                }
+            })
+            .collect();
+        Framebuffer::from_pixels(width, self.height(), pixels)
+    }
+
+    // Variance of each pixel's mean luminance, which is how much noise is
+    // left in it.
+    pub fn variance(&self) -> Vec<f64> {
+        self.sum
+            .pixels()
+            .iter()
+            .zip(self.squares.iter().zip(self.samples.iter()))
+            .map(|(sum, (&squares, &count))| mean_variance(sum, squares, count))
+            .collect()
+    }
+}
+
+// Variance of the mean luminance of `count` samples, given their sum and the
+// sum of their squared luminance. Fewer than two samples give no estimate
+// and count as infinitely noisy.
+pub fn mean_variance(sum: &Color, squares: f64, count: usize) -> f64 {
+    if count < 2 {
+        return f64::INFINITY;
+    }
+    let n = count as f64;
+    let mean = sum.luminance() / n;
+    ((squares - n * mean * mean) / (n - 1.0)).max(0.0) / n
+}
@@ -0,0 +1,131 @@
+use crate::data::Color;
+
+use super::accumulator::mean_variance;
+use super::tile::TileBuffer;
+use super::{Accumulator, Framebuffer};
+
+// Pixels darker than this are judged as if they were this bright, so noise
+// in nearly black areas does not keep them going to the sample limit.
+const DARK: f64 = 0.05;
+
+// Stops sampling a pixel once the 95% confidence interval of its mean
+// luminance is narrower than `threshold` times the mean, but not before it
+// has `min_samples`. The render's samples per pixel stay the upper bound.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    pub threshold: f64,
+    pub min_samples: usize,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> AdaptiveSampling {
+        AdaptiveSampling {
+            threshold: 0.05,
+            min_samples: 16,
+        }
+    }
+}
+
+impl AdaptiveSampling {
+    fn converged(&self, sum: &Color, squares: f64, count: usize) -> bool {
+        if count < self.min_samples.max(2) {
+            return false;
+        }
+        let mean = sum.luminance() / count as f64;
+        let half_width = 1.96 * mean_variance(sum, squares, count).sqrt();
+        half_width <= self.threshold * mean.max(DARK)
+    }
+
+    // Keeps the converged pixels of a buffer at the samples they have. A
+    // pixel only stops when its neighbours have converged too, so a lucky
+    // low variance estimate does not end it early; looking only within the
+    // tile makes the decision independent of the order tiles finish in.
+    pub fn stop_converged(&self, buffer: &mut TileBuffer) {
+        let (width, height) = (buffer.tile.width, buffer.tile.height);
+        let converged: Vec<bool> = (0..buffer.tile.len())
+            .map(|k| self.converged(&buffer.pixels[k], buffer.squares[k], buffer.counts[k]))
+            .collect();
+        for k in 0..buffer.tile.len() {
+            let (x, y) = (k % width, k / width);
+            let done = (y.saturating_sub(1)..(y + 2).min(height)).all(|qy| {
+                (x.saturating_sub(1)..(x + 2).min(width)).all(|qx| converged[qy * width + qx])
+            });
+            if done {
+                buffer.targets[k] = buffer.counts[k];
+            }
+        }
+    }
+}
+
+// Ramp from dark blue at 0 through green and yellow to red at 1.
+pub fn heat_color(t: f64) -> Color {
+    let stops = [
+        Color::new(0.0, 0.0, 0.3),
+        Color::new(0.0, 0.3, 1.0),
+        Color::new(0.0, 0.9, 0.3),
+        Color::new(1.0, 0.9, 0.0),
+        Color::new(1.0, 0.0, 0.0),
+    ];
+    let last = (stops.len() - 1) as f64;
+    let t = t.clamp(0.0, 1.0) * last;
+    let i = (t as usize).min(stops.len() - 2);
+    let f = t - i as f64;
+    stops[i] * (1.0 - f) + stops[i + 1] * f
+}
+
+// Colors every pixel by the samples it took, from dark blue for none to red
+// for `samples_per_pixel`.
+pub fn heatmap(accumulator: &Accumulator, samples_per_pixel: usize) -> Framebuffer {
+    let pixels = accumulator
+        .samples()
+        .iter()
+        .map(|&count| heat_color(count as f64 / samples_per_pixel as f64))
+        .collect();
+    Framebuffer::from_pixels(accumulator.width(), accumulator.height(), pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::worlds::three_balls;
+    use crate::engine::CameraConfig;
+    use crate::render::{Accumulator, RenderSettings, Renderer};
+
+    use super::AdaptiveSampling;
+
+    #[test]
+    fn stops_early_where_the_image_is_flat() {
+        let settings = RenderSettings {
+            width: 32,
+            height: 18,
+            samples_per_pixel: 64,
+            seed: Some(5),
+            tile_size: 8,
+            adaptive: Some(AdaptiveSampling {
+                threshold: 0.02,
+                min_samples: 8,
+            }),
+            ..RenderSettings::default()
+        };
+        let camera = CameraConfig::default().build(settings.aspect_ratio());
+        let render = |threads| {
+            let mut accumulator = Accumulator::new(32, 18);
+            let renderer = Renderer::new(RenderSettings {
+                threads,
+                ..settings.clone()
+            });
+            assert!(renderer.render_into(three_balls(), &camera, &mut accumulator, |_, _| {}));
+            accumulator
+        };
+
+        let accumulator = render(1);
+        let samples = accumulator.samples();
+        assert!(samples.iter().all(|&n| (8..=64).contains(&n)));
+        // the sky in the top row is smooth, the balls further down are not
+        assert!(samples[..32].iter().all(|&n| n < 64));
+        assert!(samples.contains(&64));
+
+        let again = render(3);
+        assert_eq!(again.samples(), samples);
+        assert_eq!(again.resolve().pixels(), accumulator.resolve().pixels());
+    }
+}
@@ -0,0 +1,285 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::data::{Color, Vec3};
+use crate::engine::{Camera, HitRecord, Hittable};
+use crate::util::rng::sample_rng;
+use crate::util::thread_pool::RTThreadPool;
+
+use super::tile::{tiles, Tile};
+use super::{Framebuffer, RenderSettings, Renderer};
+
+// Albedo and normals are averaged over the first few camera samples of each
+// pixel, the same rays the beauty pass starts with.
+pub const AOV_SAMPLES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+    Albedo,
+    Normal,
+    Depth,
+    ObjectId,
+    MaterialId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 5] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Depth,
+        Aov::ObjectId,
+        Aov::MaterialId,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+        }
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Aov, String> {
+        Aov::ALL
+            .iter()
+            .find(|aov| aov.name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown AOV '{}'", s))
+    }
+}
+
+// a random but fixed color for every id, black for 0
+pub fn id_color(id: u32) -> Color {
+    match id {
+        0 => Color::zero(),
+        _ => {
+            let mut rng = sample_rng(id as u64, 0, 0);
+            Color::new(rng.gen(), rng.gen(), rng.gen())
+        }
+    }
+}
+
+struct AovTile {
+    tile: Tile,
+    albedo: Vec<Color>,
+    normal: Vec<Color>,
+    depth: Vec<f64>,
+    object_id: Vec<u32>,
+    material_id: Vec<u32>,
+}
+
+// Per-pixel data about the first surface seen through each pixel. Depth is
+// the distance from the camera, infinite where nothing was hit; ids are
+// those the world was built with, starting at 1 with 0 meaning background.
+pub struct Aovs {
+    width: usize,
+    height: usize,
+    pub albedo: Vec<Color>,
+    pub normal: Vec<Color>,
+    pub depth: Vec<f64>,
+    pub object_id: Vec<u32>,
+    pub material_id: Vec<u32>,
+}
+
+impl Aovs {
+    pub fn new(width: usize, height: usize) -> Aovs {
+        Aovs {
+            width,
+            height,
+            albedo: vec![Color::zero(); width * height],
+            normal: vec![Color::zero(); width * height],
+            depth: vec![f64::INFINITY; width * height],
+            object_id: vec![0; width * height],
+            material_id: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // something viewable in an ordinary image: normals mapped to [0, 1],
+    // depth divided by the farthest hit and ids turned into random colors
+    pub fn image(&self, aov: Aov) -> Framebuffer {
+        let pixels = match aov {
+            Aov::Albedo => self.albedo.clone(),
+            Aov::Normal => self
+                .normal
+                .iter()
+                .map(|&n| 0.5 * (n + Color::new(1.0, 1.0, 1.0)))
+                .collect(),
+            Aov::Depth => {
+                let far = self
+                    .depth
+                    .iter()
+                    .filter(|d| d.is_finite())
+                    .fold(0.0, |a: f64, &b| a.max(b));
+                self.depth
+                    .iter()
+                    .map(|&d| {
+                        if d.is_finite() && far > 0.0 {
+                            Color::new(1.0, 1.0, 1.0) * (d / far)
+                        } else {
+                            Color::new(1.0, 1.0, 1.0)
+                        }
+                    })
+                    .collect()
+            }
+            Aov::ObjectId => self.object_id.iter().map(|&id| id_color(id)).collect(),
+            Aov::MaterialId => self.material_id.iter().map(|&id| id_color(id)).collect(),
+        };
+        Framebuffer::from_pixels(self.width, self.height, pixels)
+    }
+}
+
+impl Renderer {
+    pub fn render_aovs(&self, world: Arc<dyn Hittable + Send + Sync>, camera: &Camera) -> Aovs {
+        let RenderSettings {
+            width,
+            height,
+            samples_per_pixel,
+            background,
+            threads,
+            seed,
+            sampler,
+            tile_size,
+            tile_order,
+            ..
+        } = *self.settings();
+        let seed = seed.unwrap();
+        let samples = samples_per_pixel.min(AOV_SAMPLES);
+        let camera = camera.clone();
+
+        let shade = move |buffer: &mut AovTile| {
+            let mut sampler = sampler.sampler(seed, samples_per_pixel);
+            let Tile { x, y, width: w, .. } = buffer.tile;
+            for k in 0..buffer.tile.len() {
+                let (i, j) = (x + k % w, height - 1 - (y + k / w));
+                let pixel = (j * width + i) as u64;
+                let mut albedo = Color::zero();
+                let mut normal = Vec3::zero();
+                for sample in 0..samples {
+                    sampler.start_sample(pixel, sample as u64);
+                    let (du, dv) = sampler.get_2d();
+                    let u = (i as f64 + du) / width as f64;
+                    let v = (j as f64 + dv) / height as f64;
+                    let ray = camera.get_ray(u, v, sampler.as_mut());
+                    let mut rec = HitRecord::empty();
+                    if !world.hit(&ray, 0.001, f64::INFINITY, &mut rec) {
+                        albedo += background.value(&ray).clamp(0.0, 1.0);
+                        continue;
+                    }
+                    albedo += rec.mat_ptr.albedo(rec.u, rec.v, &rec.p);
+                    normal += rec.normal;
+                    let depth = (rec.p - *ray.origin()).len();
+                    buffer.depth[k] = buffer.depth[k].min(depth);
+                    if sample == 0 {
+                        buffer.object_id[k] = rec.object_id as u32;
+                        buffer.material_id[k] = rec.material_id as u32;
+                    }
+                }
+                buffer.albedo[k] = albedo / samples as f64;
+                buffer.normal[k] = normal / samples as f64;
+            }
+        };
+
+        let mut aovs = Aovs::new(width, height);
+        let buffers = tiles(width, height, tile_size, tile_order)
+            .into_iter()
+            .map(|tile| AovTile {
+                tile,
+                albedo: vec![Color::zero(); tile.len()],
+                normal: vec![Color::zero(); tile.len()],
+                depth: vec![f64::INFINITY; tile.len()],
+                object_id: vec![0; tile.len()],
+                material_id: vec![0; tile.len()],
+            })
+            .collect();
+        RTThreadPool::new(threads).render(buffers, shade, |buffer| {
+            let Tile { x, y, width: w, .. } = buffer.tile;
+            for k in 0..buffer.tile.len() {
+                let index = (y + k / w) * width + x + k % w;
+                aovs.albedo[index] = buffer.albedo[k];
+                aovs.normal[index] = buffer.normal[k];
+                aovs.depth[index] = buffer.depth[k];
+                aovs.object_id[index] = buffer.object_id[k];
+                aovs.material_id[index] = buffer.material_id[k];
+            }
+        });
+
+        aovs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::data::{Color, Lambertian, Point3, Vec3};
+    use crate::engine::{CameraConfig, HittableList, Translate, XYRect};
+    use crate::render::{RenderSettings, Renderer};
+
+    #[test]
+    fn aovs_describe_the_first_hit() {
+        // three upright panels side by side, the outer two sharing a
+        // material and the middle one set back by 1
+        let red = Arc::new(Lambertian::from_rgb(0.8, 0.1, 0.1));
+        let blue = Arc::new(Lambertian::from_rgb(0.1, 0.1, 0.8));
+        let mut right = HittableList::new();
+        right.add(Arc::new(XYRect::new(
+            1.0,
+            10.0,
+            -10.0,
+            10.0,
+            0.0,
+            red.clone(),
+        )));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Translate::new(
+            Arc::new(XYRect::new(-11.0, -2.0, -10.0, 10.0, 0.0, red)),
+            Vec3::new(1.0, 0.0, 0.0),
+        )));
+        world.add(Arc::new(XYRect::new(-1.0, 1.0, -10.0, 10.0, -1.0, blue)));
+        world.add(Arc::new(right));
+
+        let settings = RenderSettings {
+            width: 30,
+            height: 10,
+            seed: Some(2),
+            ..RenderSettings::default()
+        };
+        let camera = CameraConfig {
+            look_from: Point3::new(0.0, 0.0, 5.0),
+            look_at: Point3::zero(),
+            ..CameraConfig::default()
+        }
+        .build(settings.aspect_ratio());
+        let aovs = Renderer::new(settings).render_aovs(world.build(), &camera);
+
+        let row = 5 * 30;
+        let (left, middle, right) = (row, row + 15, row + 29);
+        assert_eq!(aovs.albedo[left], Color::new(0.8, 0.1, 0.1));
+        assert_eq!(aovs.albedo[middle], Color::new(0.1, 0.1, 0.8));
+        assert_eq!(aovs.albedo[right], Color::new(0.8, 0.1, 0.1));
+        for &pixel in [left, middle, right].iter() {
+            assert_eq!(aovs.normal[pixel], Vec3::new(0.0, 0.0, 1.0));
+        }
+        assert!((aovs.depth[middle] - 6.0).abs() < 0.01);
+        assert!(aovs.depth[left] > 5.0 && aovs.depth[left] < aovs.depth[middle] + 2.0);
+        let ids = |ids: &[u32]| (ids[left], ids[middle], ids[right]);
+        assert_eq!(ids(&aovs.object_id), (1, 2, 3));
+        assert_eq!(ids(&aovs.material_id), (1, 2, 1));
+    }
+}
@@ -0,0 +1,524 @@
+// A checkpoint holds everything needed to continue a render: the scene and
+// camera it belongs to, the settings, the running sums and the per-pixel
+// sample counts. Every sample draws from an RNG seeded by (seed, pixel,
+// sample index), so the seed and the counts are the complete RNG state.
+//
+// Layout, all numbers little endian: the magic bytes, the length of the
+// scene name as u64 followed by the name in UTF-8 and the scene hash as
+// u64, the camera's look_from, look_at and up as three f64 each and its
+// vfov, aperture and focus_dist as f64, then width, height,
+// samples_per_pixel, max_depth, seed and tile_size as u64, tile order,
+// progressive and background kind as u8 followed by the background color
+// as three f64, whether sampling is adaptive as u8 followed by its
+// threshold as f64 and minimum samples as u64, the sampler as u8, the pixel
+// filter as u8 followed by its radius as f64, the integrator as u8 followed
+// by the ambient occlusion radius as f64, then for every pixel its sum as
+// three f64, its sum of squared luminance as f64 and its count as u64.
+// Last come the number of film tiles as u64 and for each the tile and its
+// area as x, y, width and height in u64, followed by every pixel of the
+// area as its weighted sum in three f64 and its weight as f64.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+use crate::data::{Color, Vec3};
+use crate::engine::{Background, CameraConfig};
+use crate::util::sampler::SamplerKind;
+
+use super::tile::{FilmTile, Tile};
+use super::{
+    Accumulator, AdaptiveSampling, DebugView, Filter, FilterKind, Framebuffer, IntegratorKind,
+    RenderSettings, TileOrder,
+};
+
+const MAGIC: &[u8; 8] = b"RTCKPT07";
+
+// Bytes every pixel takes: its sum, sum of squares and count.
+const PIXEL_SIZE: u64 = 5 * 8;
+
+// The scene a render belongs to, by the name or path it was loaded from and
+// a hash of what it was built from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneId {
+    pub name: String,
+    pub hash: u64,
+}
+
+impl SceneId {
+    pub fn new(name: &str, source: &[u8]) -> SceneId {
+        // 64 bit FNV-1a, which unlike the std hashers is stable across builds
+        let hash = source.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        SceneId {
+            name: name.to_string(),
+            hash,
+        }
+    }
+}
+
+pub struct Checkpoint {
+    pub scene: SceneId,
+    pub camera: CameraConfig,
+    pub settings: RenderSettings,
+    pub accumulator: Accumulator,
+}
+
+impl Checkpoint {
+    // Refuses to continue a render of another scene, or of the same scene
+    // after it or its camera changed.
+    pub fn check(&self, scene: &SceneId, camera: &CameraConfig) -> Result<(), String> {
+        if scene.hash != self.scene.hash {
+            return Err(if scene.name == self.scene.name {
+                format!("scene '{}' has changed since it was saved", scene.name)
+            } else {
+                format!("it is of scene '{}', not '{}'", self.scene.name, scene.name)
+            });
+        }
+        let saved = &self.camera;
+        let same = |a: &Vec3, b: &Vec3| a.x() == b.x() && a.y() == b.y() && a.z() == b.z();
+        if !same(&camera.look_from, &saved.look_from)
+            || !same(&camera.look_at, &saved.look_at)
+            || !same(&camera.up, &saved.up)
+            || camera.vfov != saved.vfov
+            || camera.aperture != saved.aperture
+            || camera.focus_dist != saved.focus_dist
+        {
+            return Err(format!(
+                "the camera of '{}' has changed since it was saved",
+                scene.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_u64<W: Write>(out: &mut W, x: u64) -> io::Result<()> {
+    out.write_all(&x.to_le_bytes())
+}
+
+fn write_vec3<W: Write>(out: &mut W, v: &Vec3) -> io::Result<()> {
+    for x in [v.x(), v.y(), v.z()].iter() {
+        out.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_tile<W: Write>(out: &mut W, tile: &Tile) -> io::Result<()> {
+    for &x in [tile.x, tile.y, tile.width, tile.height].iter() {
+        write_u64(out, x as u64)?;
+    }
+    Ok(())
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_u8<R: Read>(input: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    input.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_vec3<R: Read>(input: &mut R) -> io::Result<Vec3> {
+    let mut next = || read_u64(input).map(f64::from_bits);
+    Ok(Vec3::new(next()?, next()?, next()?))
+}
+
+fn read_tile<R: Read>(input: &mut R) -> io::Result<Tile> {
+    let mut next = || read_u64(input).map(|x| x as usize);
+    Ok(Tile {
+        x: next()?,
+        y: next()?,
+        width: next()?,
+        height: next()?,
+    })
+}
+
+// Writes to a temporary file first so a crash while saving leaves the
+// previous checkpoint intact.
+pub fn save_checkpoint(
+    path: &Path,
+    scene: &SceneId,
+    camera: &CameraConfig,
+    settings: &RenderSettings,
+    accumulator: &Accumulator,
+) -> io::Result<()> {
+    let temp = path.with_extension("tmp");
+    let mut out = BufWriter::new(File::create(&temp)?);
+
+    out.write_all(MAGIC)?;
+    write_u64(&mut out, scene.name.len() as u64)?;
+    out.write_all(scene.name.as_bytes())?;
+    write_u64(&mut out, scene.hash)?;
+    for v in [camera.look_from, camera.look_at, camera.up].iter() {
+        write_vec3(&mut out, v)?;
+    }
+    for x in [camera.vfov, camera.aperture, camera.focus_dist].iter() {
+        write_u64(&mut out, x.to_bits())?;
+    }
+    for &x in [
+        settings.width,
+        settings.height,
+        settings.samples_per_pixel,
+        settings.max_depth,
+    ]
+    .iter()
+    {
+        write_u64(&mut out, x as u64)?;
+    }
+    write_u64(&mut out, settings.seed.expect("checkpoint without a seed"))?;
+    write_u64(&mut out, settings.tile_size as u64)?;
+    let order = match settings.tile_order {
+        TileOrder::Scanline => 0,
+        TileOrder::Spiral => 1,
+    };
+    let (kind, color) = match settings.background {
+        Background::Sky => (0, Color::zero()),
+        Background::Solid(color) => (1, color),
+    };
+    out.write_all(&[order, settings.progressive as u8, kind])?;
+    write_vec3(&mut out, &color)?;
+    let adaptive = settings.adaptive.unwrap_or_default();
+    out.write_all(&[settings.adaptive.is_some() as u8])?;
+    write_u64(&mut out, adaptive.threshold.to_bits())?;
+    write_u64(&mut out, adaptive.min_samples as u64)?;
+    let sampler = match settings.sampler {
+        SamplerKind::Random => 0,
+        SamplerKind::Stratified => 1,
+        SamplerKind::Halton => 2,
+        SamplerKind::Sobol => 3,
+    };
+    let filter = match settings.filter.kind {
+        FilterKind::Box => 0,
+        FilterKind::Tent => 1,
+        FilterKind::Gaussian => 2,
+        FilterKind::Mitchell => 3,
+        FilterKind::Lanczos => 4,
+    };
+    out.write_all(&[sampler, filter])?;
+    write_u64(&mut out, settings.filter.radius.to_bits())?;
+    let (integrator, ao_radius) = match settings.integrator {
+        IntegratorKind::Path => (0, 0.0),
+        IntegratorKind::Direct => (1, 0.0),
+        IntegratorKind::AmbientOcclusion { radius } => (2, radius),
+        IntegratorKind::Debug(view) => match view {
+            DebugView::Normals => (3, 0.0),
+            DebugView::Uv => (4, 0.0),
+            DebugView::Distance => (5, 0.0),
+            DebugView::BvhCost => (6, 0.0),
+            DebugView::MaterialId => (7, 0.0),
+        },
+    };
+    out.write_all(&[integrator])?;
+    write_u64(&mut out, ao_radius.to_bits())?;
+
+    let pixels = accumulator.sum().pixels().iter().zip(accumulator.squares());
+    for ((sum, &squares), &count) in pixels.zip(accumulator.samples()) {
+        write_vec3(&mut out, sum)?;
+        write_u64(&mut out, squares.to_bits())?;
+        write_u64(&mut out, count as u64)?;
+    }
+    write_u64(&mut out, accumulator.film().count() as u64)?;
+    for film in accumulator.film() {
+        write_tile(&mut out, &film.tile)?;
+        write_tile(&mut out, &film.area)?;
+        for (color, &weight) in film.weighted.iter().zip(&film.weights) {
+            write_vec3(&mut out, color)?;
+            write_u64(&mut out, weight.to_bits())?;
+        }
+    }
+    out.into_inner()?.sync_all()?;
+    fs::rename(&temp, path)
+}
+
+pub fn load_checkpoint(path: &Path) -> io::Result<Checkpoint> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut input = BufReader::new(file);
+
+    let mut magic = [0; 8];
+    input.read_exact(&mut magic)?;
+    if magic.starts_with(b"RTCKPT") && &magic != MAGIC {
+        return Err(invalid("checkpoint written by an older version"));
+    }
+    if &magic != MAGIC {
+        return Err(invalid("not a render checkpoint"));
+    }
+    let name_len = read_u64(&mut input)?;
+    if name_len > size {
+        return Err(invalid("scene name past the end of the file"));
+    }
+    let mut name = vec![0; name_len as usize];
+    input.read_exact(&mut name)?;
+    let name = String::from_utf8(name).map_err(|_| invalid("scene name is not UTF-8"))?;
+    let scene = SceneId {
+        name,
+        hash: read_u64(&mut input)?,
+    };
+    let mut next = || read_u64(&mut input).map(f64::from_bits);
+    let (look_from, look_at, up) = (
+        Vec3::new(next()?, next()?, next()?),
+        Vec3::new(next()?, next()?, next()?),
+        Vec3::new(next()?, next()?, next()?),
+    );
+    let camera = CameraConfig {
+        look_from,
+        look_at,
+        up,
+        vfov: next()?,
+        aperture: next()?,
+        focus_dist: next()?,
+    };
+    let width = read_u64(&mut input)? as usize;
+    let height = read_u64(&mut input)? as usize;
+    let samples_per_pixel = read_u64(&mut input)? as usize;
+    let max_depth = read_u64(&mut input)? as usize;
+    let seed = read_u64(&mut input)?;
+    let tile_size = read_u64(&mut input)? as usize;
+    let tile_order = match read_u8(&mut input)? {
+        0 => TileOrder::Scanline,
+        1 => TileOrder::Spiral,
+        _ => return Err(invalid("unknown tile order")),
+    };
+    let progressive = read_u8(&mut input)? != 0;
+    let kind = read_u8(&mut input)?;
+    let color = read_vec3(&mut input)?;
+    let background = match kind {
+        0 => Background::Sky,
+        1 => Background::Solid(color),
+        _ => return Err(invalid("unknown background")),
+    };
+    let is_adaptive = read_u8(&mut input)? != 0;
+    let threshold = f64::from_bits(read_u64(&mut input)?);
+    let min_samples = read_u64(&mut input)? as usize;
+    let adaptive = if is_adaptive {
+        Some(AdaptiveSampling {
+            threshold,
+            min_samples,
+        })
+    } else {
+        None
+    };
+    let sampler = match read_u8(&mut input)? {
+        0 => SamplerKind::Random,
+        1 => SamplerKind::Stratified,
+        2 => SamplerKind::Halton,
+        3 => SamplerKind::Sobol,
+        _ => return Err(invalid("unknown sampler")),
+    };
+    let kind = match read_u8(&mut input)? {
+        0 => FilterKind::Box,
+        1 => FilterKind::Tent,
+        2 => FilterKind::Gaussian,
+        3 => FilterKind::Mitchell,
+        4 => FilterKind::Lanczos,
+        _ => return Err(invalid("unknown pixel filter")),
+    };
+    let radius = f64::from_bits(read_u64(&mut input)?);
+    if radius.is_nan() || radius <= 0.0 {
+        return Err(invalid("pixel filter without a radius"));
+    }
+    let integrator = read_u8(&mut input)?;
+    let ao_radius = f64::from_bits(read_u64(&mut input)?);
+    let integrator = match integrator {
+        0 => IntegratorKind::Path,
+        1 => IntegratorKind::Direct,
+        2 if ao_radius > 0.0 => IntegratorKind::AmbientOcclusion { radius: ao_radius },
+        3 => IntegratorKind::Debug(DebugView::Normals),
+        4 => IntegratorKind::Debug(DebugView::Uv),
+        5 => IntegratorKind::Debug(DebugView::Distance),
+        6 => IntegratorKind::Debug(DebugView::BvhCost),
+        7 => IntegratorKind::Debug(DebugView::MaterialId),
+        _ => return Err(invalid("unknown integrator")),
+    };
+    let settings = RenderSettings {
+        width,
+        height,
+        samples_per_pixel,
+        max_depth,
+        integrator,
+        background,
+        seed: Some(seed),
+        tile_size,
+        tile_order,
+        progressive,
+        adaptive,
+        sampler,
+        filter: Filter { kind, radius },
+        ..RenderSettings::default()
+    };
+    if settings.width == 0 || settings.height == 0 || settings.tile_size == 0 {
+        return Err(invalid("empty image"));
+    }
+
+    // a corrupt size must fail here rather than in the allocations below
+    let remaining = size.saturating_sub(input.stream_position()?);
+    let len = settings
+        .width
+        .checked_mul(settings.height)
+        .filter(|&len| (len as u64).saturating_mul(PIXEL_SIZE) <= remaining)
+        .ok_or_else(|| invalid("image larger than the checkpoint"))?;
+    let mut sums = Vec::with_capacity(len);
+    let mut squares = Vec::with_capacity(len);
+    let mut samples = Vec::with_capacity(len);
+    for _ in 0..len {
+        sums.push(read_vec3(&mut input)?);
+        squares.push(f64::from_bits(read_u64(&mut input)?));
+        samples.push(read_u64(&mut input)? as usize);
+    }
+    let sum = Framebuffer::from_pixels(settings.width, settings.height, sums);
+
+    let count = read_u64(&mut input)? as usize;
+    if count > len {
+        return Err(invalid("too many film tiles"));
+    }
+    let mut film = Vec::with_capacity(count);
+    for _ in 0..count {
+        let tile = read_tile(&mut input)?;
+        let area = read_tile(&mut input)?;
+        let inside = |inner: &Tile, outer: &Tile| {
+            outer.x <= inner.x
+                && outer.y <= inner.y
+                && inner.x + inner.width <= outer.x + outer.width
+                && inner.y + inner.height <= outer.y + outer.height
+        };
+        let image = Tile {
+            x: 0,
+            y: 0,
+            width: settings.width,
+            height: settings.height,
+        };
+        if tile.is_empty() || !inside(&tile, &area) || !inside(&area, &image) {
+            return Err(invalid("film tile outside the image"));
+        }
+        let mut tile = FilmTile::new(tile, area);
+        for k in 0..area.len() {
+            tile.weighted[k] = read_vec3(&mut input)?;
+            tile.weights[k] = f64::from_bits(read_u64(&mut input)?);
+        }
+        film.push(tile);
+    }
+
+    Ok(Checkpoint {
+        scene,
+        camera,
+        settings,
+        accumulator: Accumulator::from_parts(sum, squares, samples, film),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::sync::atomic::Ordering;
+
+    use crate::data::worlds::three_balls;
+    use crate::engine::CameraConfig;
+    use crate::render::{Accumulator, Filter, FilterKind, RenderSettings, Renderer};
+
+    use super::{load_checkpoint, save_checkpoint, SceneId};
+
+    // a file of its own for every test and test run
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("rust_tracer_checkpoint_tests");
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(format!("{}-{}.ckpt", name, std::process::id()))
+    }
+
+    #[test]
+    fn resume_matches_uninterrupted() {
+        let settings = RenderSettings {
+            width: 20,
+            height: 12,
+            samples_per_pixel: 6,
+            seed: Some(3),
+            tile_size: 4,
+            progressive: true,
+            threads: 2,
+            filter: Filter::new(FilterKind::Mitchell),
+            ..RenderSettings::default()
+        };
+        let scene = SceneId::new("three_balls", b"three_balls");
+        let config = CameraConfig::default();
+        let camera = config.build(settings.aspect_ratio());
+        let expected = Renderer::new(settings.clone()).render(three_balls(), &camera);
+
+        let renderer = Renderer::new(settings);
+        let cancel = renderer.cancel_flag();
+        let mut tiles = 0;
+        let mut accumulator = Accumulator::new(20, 12);
+        let finished = renderer.render_into(three_balls(), &camera, &mut accumulator, |_, _| {
+            tiles += 1;
+            if tiles == 20 {
+                cancel.store(true, Ordering::SeqCst);
+            }
+        });
+        assert!(!finished);
+
+        let path = temp_path("resume");
+        save_checkpoint(&path, &scene, &config, renderer.settings(), &accumulator).unwrap();
+        let mut checkpoint = load_checkpoint(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(checkpoint.scene, scene);
+        assert!(checkpoint.check(&scene, &config).is_ok());
+
+        let renderer = Renderer::new(checkpoint.settings);
+        let finished = renderer.render_into(
+            three_balls(),
+            &camera,
+            &mut checkpoint.accumulator,
+            |_, _| {},
+        );
+        assert!(finished);
+        assert_eq!(checkpoint.accumulator.resolve().pixels(), expected.pixels());
+    }
+
+    #[test]
+    fn refuses_other_scenes_and_corrupt_sizes() {
+        let settings = RenderSettings {
+            width: 4,
+            height: 4,
+            seed: Some(1),
+            ..RenderSettings::default()
+        };
+        let scene = SceneId::new("room.scene", b"render { width 4 }");
+        let config = CameraConfig::default();
+        let path = temp_path("refuses");
+        save_checkpoint(&path, &scene, &config, &settings, &Accumulator::new(4, 4)).unwrap();
+        let checkpoint = load_checkpoint(&path).unwrap();
+
+        let edited = SceneId::new("room.scene", b"render { width 8 }");
+        let other = SceneId::new("cornell_box", b"cornell_box");
+        let moved = CameraConfig {
+            vfov: 30.0,
+            ..config.clone()
+        };
+        assert_eq!(
+            checkpoint.check(&edited, &config),
+            Err("scene 'room.scene' has changed since it was saved".to_string())
+        );
+        assert_eq!(
+            checkpoint.check(&other, &config),
+            Err("it is of scene 'room.scene', not 'cornell_box'".to_string())
+        );
+        assert!(checkpoint.check(&scene, &moved).is_err());
+
+        // a width far beyond what the file holds is an error, not an abort
+        let mut bytes = fs::read(&path).unwrap();
+        let width = 8 + 8 + scene.name.len() + 8 + 12 * 8;
+        bytes[width..width + 8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(load_checkpoint(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+use crate::data::Color;
+
+use super::{Aovs, Framebuffer};
+
+// B3 spline, the usual à-trous kernel
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010) with the
+// variance guided luminance weights of SVGF (Schied et al. 2017). Every pass
+// blurs with a 5x5 kernel whose taps are twice as far apart as in the pass
+// before, weighting each tap down where its normal, albedo or depth differs
+// from the center pixel, or where its brightness differs by more than the
+// noise left in the two pixels explains. The image is divided by the
+// albedo first so that textures stay sharp and only the lighting gets
+// smoothed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    pub iterations: usize,
+    pub sigma_luminance: f64,
+    pub sigma_normal: f64,
+    pub sigma_albedo: f64,
+    pub sigma_depth: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Denoiser {
+        Denoiser {
+            iterations: 5,
+            sigma_luminance: 4.0,
+            sigma_normal: 0.3,
+            sigma_albedo: 0.1,
+            sigma_depth: 0.05,
+        }
+    }
+}
+
+fn clean(x: f64) -> f64 {
+    if x.is_finite() {
+        x.max(0.0)
+    } else {
+        0.0
+    }
+}
+
+// relative depth difference, scaled by how far apart the taps are
+fn depth_weight(a: f64, b: f64, scale: f64) -> f64 {
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => (-(a - b).abs() / (scale * a.min(b).max(1e-9))).exp(),
+        (false, false) => 1.0,
+        _ => 0.0,
+    }
+}
+
+// 3x3 gaussian blur, which makes the per-pixel variance estimates a little
+// less noisy themselves
+fn blur(values: &[f64], width: usize, height: usize) -> Vec<f64> {
+    let kernel = [0.25, 0.5, 0.25];
+    let mut blurred = Vec::with_capacity(values.len());
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            let mut total = 0.0;
+            for (dy, ky) in kernel.iter().enumerate() {
+                for (dx, kx) in kernel.iter().enumerate() {
+                    let (qx, qy) = ((x + dx).wrapping_sub(1), (y + dy).wrapping_sub(1));
+                    if qx < width && qy < height {
+                        sum += kx * ky * values[qy * width + qx];
+                        total += kx * ky;
+                    }
+                }
+            }
+            blurred.push(sum / total);
+        }
+    }
+    blurred
+}
+
+impl Denoiser {
+    // `variance` is the variance of every pixel's luminance, as estimated
+    // by `Accumulator::variance`.
+    pub fn denoise(&self, image: &Framebuffer, variance: &[f64], aovs: &Aovs) -> Framebuffer {
+        let (width, height) = (image.width(), image.height());
+        assert!(aovs.width() == width && aovs.height() == height);
+        assert_eq!(variance.len(), width * height);
+
+        // channels with (nearly) black albedo are filtered as they are
+        let factors: Vec<Color> = aovs
+            .albedo
+            .iter()
+            .map(|a| {
+                let factor = |x: f64| if x > 1e-3 { x } else { 1.0 };
+                Color::new(factor(a.x()), factor(a.y()), factor(a.z()))
+            })
+            .collect();
+        let mut current: Vec<Color> = image
+            .pixels()
+            .iter()
+            .zip(&factors)
+            .map(|(color, factor)| {
+                Color::new(
+                    clean(color.x()) / factor.x(),
+                    clean(color.y()) / factor.y(),
+                    clean(color.z()) / factor.z(),
+                )
+            })
+            .collect();
+        let mut variance: Vec<f64> = variance
+            .iter()
+            .zip(&factors)
+            .map(|(&v, factor)| v / factor.luminance().powi(2))
+            .collect();
+
+        for i in 0..self.iterations {
+            let step = 1isize << i;
+            let blurred = blur(&variance, width, height);
+            let mut next = Vec::with_capacity(current.len());
+            let mut next_variance = Vec::with_capacity(current.len());
+            for y in 0..height {
+                for x in 0..width {
+                    let p = y * width + x;
+                    let luminance = current[p].luminance();
+                    let mut sum = Color::zero();
+                    let mut sum_variance = 0.0;
+                    let mut total = 0.0;
+                    for (dy, ky) in KERNEL.iter().enumerate() {
+                        let qy = y as isize + (dy as isize - 2) * step;
+                        if qy < 0 || qy >= height as isize {
+                            continue;
+                        }
+                        for (dx, kx) in KERNEL.iter().enumerate() {
+                            let qx = x as isize + (dx as isize - 2) * step;
+                            if qx < 0 || qx >= width as isize {
+                                continue;
+                            }
+                            let q = qy as usize * width + qx as usize;
+                            let exponent = (luminance - current[q].luminance()).abs()
+                                / (self.sigma_luminance * (blurred[p] + blurred[q]).sqrt() + 1e-10)
+                                + (aovs.normal[p] - aovs.normal[q]).len_sq()
+                                    / (self.sigma_normal * self.sigma_normal)
+                                + (aovs.albedo[p] - aovs.albedo[q]).len_sq()
+                                    / (self.sigma_albedo * self.sigma_albedo);
+                            let weight = kx
+                                * ky
+                                * (-exponent).exp()
+                                * depth_weight(
+                                    aovs.depth[p],
+                                    aovs.depth[q],
+                                    self.sigma_depth * step as f64,
+                                );
+                            if weight > 0.0 {
+                                sum += weight * current[q];
+                                sum_variance += weight * weight * variance[q];
+                                total += weight;
+                            }
+                        }
+                    }
+                    // the center tap always has weight, so total > 0
+                    next.push(sum / total);
+                    next_variance.push(sum_variance / (total * total));
+                }
+            }
+            current = next;
+            variance = next_variance;
+        }
+
+        let pixels = current
+            .iter()
+            .zip(&factors)
+            .map(|(&color, &factor)| color * factor)
+            .collect();
+        Framebuffer::from_pixels(width, height, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::data::{Color, Vec3};
+    use crate::render::{Aovs, Framebuffer};
+    use crate::util::rng::seeded_rng;
+
+    use super::Denoiser;
+
+    fn error(a: &Framebuffer, b: &[Color]) -> f64 {
+        let sum: f64 = a
+            .pixels()
+            .iter()
+            .zip(b)
+            .map(|(x, y)| (*x - *y).len_sq())
+            .sum();
+        sum / b.len() as f64
+    }
+
+    // left half faces +x and is lit brightly, right half faces +y and is dim
+    fn scene(width: usize, height: usize) -> (Vec<Color>, Aovs) {
+        let mut aovs = Aovs::new(width, height);
+        let mut clean = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let p = y * width + x;
+                let left = x < width / 2;
+                aovs.normal[p] = if left {
+                    Vec3::new(1.0, 0.0, 0.0)
+                } else {
+                    Vec3::new(0.0, 1.0, 0.0)
+                };
+                // checkered albedo that the filter must not blur
+                aovs.albedo[p] = if (x / 2 + y / 2) % 2 == 0 {
+                    Color::new(0.8, 0.2, 0.2)
+                } else {
+                    Color::new(0.2, 0.8, 0.2)
+                };
+                aovs.depth[p] = 1.0 + x as f64 * 0.01;
+                let light = if left { 1.0 } else { 0.1 };
+                clean.push(aovs.albedo[p] * light);
+            }
+        }
+        (clean, aovs)
+    }
+
+    #[test]
+    fn removes_noise_and_keeps_edges() {
+        let (width, height) = (32, 24);
+        let (clean, aovs) = scene(width, height);
+        let mut rng = seeded_rng(1);
+        let noisy: Vec<Color> = clean
+            .iter()
+            .map(|&c| c * (1.0 + rng.gen_range(-0.5..0.5)))
+            .collect();
+        let noisy = Framebuffer::from_pixels(width, height, noisy);
+        // variance of a uniform distribution of that width
+        let variance: Vec<f64> = clean.iter().map(|c| c.luminance().powi(2) / 12.0).collect();
+
+        let denoised = Denoiser::default().denoise(&noisy, &variance, &aovs);
+        assert!(error(&denoised, &clean) < error(&noisy, &clean) / 10.0);
+
+        // nothing leaks across the edge between the two halves
+        for y in 0..height {
+            for &x in [width / 2 - 1, width / 2].iter() {
+                let p = y * width + x;
+                let expected = clean[p];
+                let got = denoised.get(x, y);
+                assert!((got - expected).len() < 0.1 * expected.len());
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_clean_images_alone() {
+        let (width, height) = (16, 16);
+        let (clean, aovs) = scene(width, height);
+        let image = Framebuffer::from_pixels(width, height, clean.clone());
+        let variance = vec![0.0; width * height];
+        let denoised = Denoiser::default().denoise(&image, &variance, &aovs);
+        assert!(error(&denoised, &clean) < 1e-20);
+    }
+}
@@ -0,0 +1,158 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FromStr for FilterKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FilterKind, String> {
+        match s {
+            "box" => Ok(FilterKind::Box),
+            "tent" => Ok(FilterKind::Tent),
+            "gaussian" => Ok(FilterKind::Gaussian),
+            "mitchell" => Ok(FilterKind::Mitchell),
+            "lanczos" => Ok(FilterKind::Lanczos),
+            _ => Err(format!("unknown pixel filter '{}'", s)),
+        }
+    }
+}
+
+impl FilterKind {
+    pub fn default_radius(&self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 3.0,
+        }
+    }
+}
+
+// Reconstruction filter that weights every sample's contribution to the
+// pixels around it by its distance from their centers, in pixels. The
+// default box of radius 0.5 keeps each sample in its own pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f64,
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::new(FilterKind::Box)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind) -> Filter {
+        Filter {
+            kind,
+            radius: kind.default_radius(),
+        }
+    }
+
+    // how many pixels past its own a sample can reach
+    pub fn margin(&self) -> usize {
+        (self.radius - 0.5).ceil().max(0.0) as usize
+    }
+
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let r = self.radius;
+        match self.kind {
+            // half open, so a sample on the border of two pixels counts once
+            FilterKind::Box => {
+                if -r <= x && x < r {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            FilterKind::Tent => (1.0 - x.abs() / r).max(0.0),
+            FilterKind::Gaussian => {
+                let sigma = r / 3.0;
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(r)).max(0.0)
+            }
+            // Mitchell-Netravali with B = C = 1/3, stretched over the radius
+            FilterKind::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = 2.0 * x.abs() / r;
+                if x >= 2.0 {
+                    0.0
+                } else if x > 1.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            // sinc windowed by a wider sinc that reaches zero at the radius
+            FilterKind::Lanczos => {
+                if x.abs() >= r {
+                    0.0
+                } else {
+                    sinc(x) * sinc(x / r)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, FilterKind};
+
+    #[test]
+    fn filters_peak_in_the_middle_and_end_at_the_radius() {
+        for &kind in [
+            FilterKind::Box,
+            FilterKind::Tent,
+            FilterKind::Gaussian,
+            FilterKind::Mitchell,
+            FilterKind::Lanczos,
+        ]
+        .iter()
+        {
+            let filter = Filter::new(kind);
+            let r = filter.radius;
+            let center = filter.evaluate(0.0, 0.0);
+            assert!(center > 0.0);
+            for i in 1..100 {
+                let x = r * i as f64 / 100.0;
+                assert!(filter.evaluate(x, 0.1) <= center);
+                assert!((filter.evaluate(x, 0.3) - filter.evaluate(-x, -0.3)).abs() < 1e-12);
+            }
+            assert_eq!(filter.evaluate(r, 0.0), 0.0);
+            assert_eq!(filter.evaluate(0.0, r + 0.01), 0.0);
+        }
+        assert_eq!(Filter::default().margin(), 0);
+        assert_eq!(Filter::new(FilterKind::Mitchell).margin(), 2);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::data::Color;
+
+use super::tile::{Tile, TileBuffer};
+
+#[derive(Clone)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::zero(); width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Framebuffer {
+        assert_eq!(pixels.len(), width * height);
+        Framebuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // rows are stored top to bottom
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    pub fn read_tile(&self, tile: Tile) -> TileBuffer {
+        let mut buffer = TileBuffer::new(tile);
+        buffer.pixels = tile.read(&self.pixels, self.width);
+        buffer
+    }
+
+    pub fn write_tile(&mut self, buffer: &TileBuffer) {
+        buffer
+            .tile
+            .write(&mut self.pixels, self.width, &buffer.pixels);
+    }
+}
@@ -0,0 +1,494 @@
+use std::str::FromStr;
+
+use crate::data::{Color, ScatterRecord, Scattered};
+use crate::engine::{
+    Background, CosinePdf, HitRecord, Hittable, HittableList, HittablePdf, Pdf, Ray,
+};
+use crate::util::sampler::Sampler;
+
+use super::adaptive::heat_color;
+use super::aov::id_color;
+
+// Bounces every path takes before Russian roulette may end it.
+const ROULETTE_DEPTH: usize = 3;
+
+// Bounding volume nodes tested by one camera ray that show as red in the
+// BVH cost view.
+const BVH_COST_SCALE: f64 = 100.0;
+
+// Computes the light arriving at the camera along a ray. Surfaces that
+// scatter by a pdf sample `lights` directly too; integrators that do
+// should combine both estimates with multiple importance sampling.
+pub trait Integrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntegratorKind {
+    Path,
+    Direct,
+    // occlusion within `radius` of the first hit, unlimited by default
+    AmbientOcclusion { radius: f64 },
+    Debug(DebugView),
+}
+
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<IntegratorKind, String> {
+        match s {
+            "path" => Ok(IntegratorKind::Path),
+            "direct" => Ok(IntegratorKind::Direct),
+            "ao" => Ok(IntegratorKind::AmbientOcclusion {
+                radius: f64::INFINITY,
+            }),
+            _ => s
+                .parse()
+                .map(IntegratorKind::Debug)
+                .map_err(|_| format!("unknown integrator '{}'", s)),
+        }
+    }
+}
+
+impl IntegratorKind {
+    pub fn integrator(&self, max_depth: usize) -> Box<dyn Integrator + Send + Sync> {
+        match *self {
+            IntegratorKind::Path => Box::new(PathIntegrator { max_depth }),
+            IntegratorKind::Direct => Box::new(DirectIntegrator { max_depth }),
+            IntegratorKind::AmbientOcclusion { radius } => {
+                Box::new(AmbientOcclusionIntegrator { radius })
+            }
+            IntegratorKind::Debug(view) => Box::new(DebugIntegrator { view }),
+        }
+    }
+}
+
+// What the debug integrator shows about the first surface a camera ray
+// hits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugView {
+    // shading normal, facing the ray, mapped to [0, 1]
+    Normals,
+    // texture coordinates in red and green
+    Uv,
+    // distance from the camera, unscaled
+    Distance,
+    // bounding volume nodes the ray tested, as a heatmap
+    BvhCost,
+    // a random color per material
+    MaterialId,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 5] = [
+        DebugView::Normals,
+        DebugView::Uv,
+        DebugView::Distance,
+        DebugView::BvhCost,
+        DebugView::MaterialId,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugView::Normals => "normals",
+            DebugView::Uv => "uv",
+            DebugView::Distance => "distance",
+            DebugView::BvhCost => "bvh_cost",
+            DebugView::MaterialId => "material_id",
+        }
+    }
+}
+
+impl FromStr for DebugView {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DebugView, String> {
+        DebugView::ALL
+            .iter()
+            .find(|view| view.name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown debug view '{}'", s))
+    }
+}
+
+// Full global illumination. Paths end after `max_depth` hits, or earlier
+// by Russian roulette once their throughput has dropped.
+pub struct PathIntegrator {
+    pub max_depth: usize,
+}
+
+impl Integrator for PathIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let path = Path {
+            world,
+            lights,
+            background,
+            max_depth: self.max_depth,
+            max_diffuse: usize::MAX,
+        };
+        path.trace(ray, sampler)
+    }
+}
+
+// Only light that reaches the first diffuse surface straight from an
+// emitter, after any number of specular bounces on the way there.
+pub struct DirectIntegrator {
+    pub max_depth: usize,
+}
+
+impl Integrator for DirectIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let path = Path {
+            world,
+            lights,
+            background,
+            max_depth: self.max_depth,
+            max_diffuse: 1,
+        };
+        path.trace(ray, sampler)
+    }
+}
+
+// Fraction of the hemisphere above the first hit that is open within
+// `radius`, estimated one cosine weighted direction per sample.
+pub struct AmbientOcclusionIntegrator {
+    pub radius: f64,
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _lights: &HittableList,
+        _background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut rec = HitRecord::empty();
+        if !world.hit(ray, 0.001, f64::INFINITY, &mut rec) {
+            return Color::zero();
+        }
+        let direction = CosinePdf::new(&rec.normal).generate(sampler.get_2d());
+        let t_max = self.radius / direction.len();
+        let mut occluder = HitRecord::empty();
+        if world.hit(&Ray::new(rec.p, direction), 0.001, t_max, &mut occluder) {
+            Color::zero()
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+// Shows one property of the first hit, black where nothing was hit.
+pub struct DebugIntegrator {
+    pub view: DebugView,
+}
+
+impl Integrator for DebugIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _lights: &HittableList,
+        _background: &Background,
+        _sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut rec = HitRecord::empty();
+        if self.view == DebugView::BvhCost {
+            let mut visits = 0;
+            world.hit_counting(ray, 0.001, f64::INFINITY, &mut rec, &mut visits);
+            return heat_color(visits as f64 / BVH_COST_SCALE);
+        }
+        let hit = world.hit(ray, 0.001, f64::INFINITY, &mut rec);
+        if !hit {
+            return Color::zero();
+        }
+        match self.view {
+            DebugView::Normals => 0.5 * (rec.normal + Color::new(1.0, 1.0, 1.0)),
+            DebugView::Uv => Color::new(rec.u, rec.v, 0.0),
+            DebugView::Distance => Color::new(1.0, 1.0, 1.0) * (rec.t * ray.dir().len()),
+            DebugView::BvhCost => unreachable!(),
+            DebugView::MaterialId => id_color(rec.material_id as u32),
+        }
+    }
+}
+
+// power heuristic weight of a strategy with density `pdf` against `other`
+fn mis_weight(pdf: f64, other: f64) -> f64 {
+    pdf * pdf / (pdf * pdf + other * other)
+}
+
+struct Path<'a> {
+    world: &'a dyn Hittable,
+    lights: &'a HittableList,
+    background: &'a Background,
+    max_depth: usize,
+    // bounces off surfaces that scatter by a pdf
+    max_diffuse: usize,
+}
+
+impl Path<'_> {
+    fn trace(&self, r: &Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut radiance = Color::zero();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = Ray::new(*r.origin(), *r.dir());
+        // density the last bounce picked `ray` with, None after the camera
+        // or a specular bounce where lights are not sampled
+        let mut scatter_pdf = None;
+        let mut diffuse = 0;
+
+        for depth in 0..self.max_depth {
+            let mut rec = HitRecord::empty();
+            if !self.world.hit(&ray, 0.001, f64::INFINITY, &mut rec) {
+                radiance += throughput * self.background.value(&ray);
+                break;
+            }
+
+            let mut emitted = rec.mat_ptr.emitted(rec.u, rec.v, &rec.p);
+            if let Some(pdf) = scatter_pdf {
+                if !self.lights.is_empty() && emitted.len_sq() > 0.0 {
+                    emitted *= mis_weight(pdf, self.lights.pdf_value(ray.origin(), ray.dir()));
+                }
+            }
+            radiance += throughput * emitted;
+
+            let mut srec = ScatterRecord::empty();
+            if diffuse == self.max_diffuse || !rec.mat_ptr.scatter(&ray, &rec, &mut srec, sampler) {
+                break;
+            }
+            let pdf = match srec.scattered {
+                Scattered::Specular(next) => {
+                    throughput = throughput * srec.attenuation;
+                    ray = next;
+                    scatter_pdf = None;
+                    continue;
+                }
+                Scattered::Pdf(pdf) => pdf,
+            };
+
+            diffuse += 1;
+            // light found by the last bounce would be past the depth limit
+            if depth + 1 < self.max_depth {
+                let direct = self.sample_light(&ray, &rec, pdf.as_ref(), sampler);
+                radiance += throughput * srec.attenuation * direct;
+            }
+            let scattered = Ray::new(rec.p, pdf.generate(sampler.get_2d()));
+            let pdf_value = pdf.value(scattered.dir());
+            let scattering_pdf = rec.mat_ptr.scattering_pdf(&ray, &rec, &scattered);
+            if pdf_value <= 0.0 || scattering_pdf <= 0.0 {
+                break;
+            }
+            throughput = throughput * srec.attenuation * (scattering_pdf / pdf_value);
+            ray = scattered;
+            scatter_pdf = Some(pdf_value);
+
+            if depth + 1 >= ROULETTE_DEPTH {
+                let survival = throughput.x().max(throughput.y()).max(throughput.z());
+                if survival < 1.0 {
+                    if sampler.get_1d() >= survival {
+                        break;
+                    }
+                    throughput = throughput / survival;
+                }
+            }
+        }
+        radiance
+    }
+
+    // Light reaching `rec` straight from a point picked on one of the
+    // lights, weighted against finding it through `pdf`, the material's own
+    // sampling, and still to be multiplied by the attenuation. Whatever the
+    // shadow ray hits first counts, so occluders simply contribute their
+    // own (usually no) emission.
+    fn sample_light(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        pdf: &dyn Pdf,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        if self.lights.is_empty() {
+            return Color::zero();
+        }
+        let light_pdf = HittablePdf::new(self.lights, rec.p);
+        let ray = Ray::new(rec.p, light_pdf.generate(sampler.get_2d()));
+        let light_value = light_pdf.value(ray.dir());
+        let scattering_pdf = rec.mat_ptr.scattering_pdf(r, rec, &ray);
+        if light_value <= 0.0 || scattering_pdf <= 0.0 {
+            return Color::zero();
+        }
+        let mut light = HitRecord::empty();
+        if !self.world.hit(&ray, 0.001, f64::INFINITY, &mut light) {
+            return Color::zero();
+        }
+        let emitted = light.mat_ptr.emitted(light.u, light.v, &light.p);
+        let weight = mis_weight(light_value, pdf.value(ray.dir()));
+        emitted * (scattering_pdf * weight / light_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    use crate::data::{worlds, Color, Material, Point3, ScatterRecord, Scattered};
+    use crate::engine::{Background, CameraConfig, CosinePdf, HitRecord, Ray, Sphere};
+    use crate::render::{RenderSettings, Renderer};
+    use crate::util::sampler::Sampler;
+
+    use super::{DebugView, IntegratorKind};
+
+    // a diffuse surface that also glows, with albedo 0.8 and emission 1
+    struct Glowing;
+
+    impl Material for Glowing {
+        fn scatter(
+            &self,
+            _r_in: &Ray,
+            rec: &HitRecord,
+            srec: &mut ScatterRecord,
+            _sampler: &mut dyn Sampler,
+        ) -> bool {
+            srec.attenuation = Color::new(0.8, 0.8, 0.8);
+            srec.scattered = Scattered::Pdf(Box::new(CosinePdf::new(&rec.normal)));
+            true
+        }
+
+        fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+            rec.normal.dot(&scattered.dir().unit()).max(0.0) / PI
+        }
+
+        fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    #[test]
+    fn furnace_matches_its_closed_form() {
+        // inside a closed sphere every bounce adds 1 and keeps 0.8 of what
+        // follows, so the path integrator must see 1 / (1 - 0.8) = 5 however
+        // Russian roulette cuts the paths short, and direct light 1 + 0.8
+        let world = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            100.0,
+            Arc::new(Glowing),
+        ));
+        let camera = CameraConfig::default().build(1.0);
+        for &(integrator, expected) in
+            [(IntegratorKind::Path, 5.0), (IntegratorKind::Direct, 1.8)].iter()
+        {
+            let renderer = Renderer::new(RenderSettings {
+                width: 8,
+                height: 8,
+                samples_per_pixel: 64,
+                max_depth: 200,
+                integrator,
+                background: Background::Solid(Color::zero()),
+                seed: Some(4),
+                ..RenderSettings::default()
+            });
+            let image = renderer.render(world.clone(), &camera);
+            let mean = image.pixels().iter().map(|c| c.luminance()).sum::<f64>() / 64.0;
+            assert!((mean - expected).abs() < 0.02 * expected, "{}", mean);
+        }
+    }
+
+    #[test]
+    fn debug_views_and_ambient_occlusion_look_at_the_first_hit() {
+        // seen from its center, a sphere is everywhere at its radius, and
+        // occludes all of the ambient light or hardly any of it
+        let world = Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            100.0,
+            Arc::new(Glowing),
+        ));
+        let camera = CameraConfig {
+            look_from: Point3::new(0.0, 0.0, 0.0),
+            look_at: Point3::new(0.0, 0.0, -1.0),
+            ..CameraConfig::default()
+        }
+        .build(1.0);
+        for &(integrator, expected) in [
+            (IntegratorKind::Debug(DebugView::Distance), 100.0),
+            (IntegratorKind::AmbientOcclusion { radius: 1.0 }, 1.0),
+            (
+                IntegratorKind::AmbientOcclusion {
+                    radius: f64::INFINITY,
+                },
+                0.0,
+            ),
+        ]
+        .iter()
+        {
+            let renderer = Renderer::new(RenderSettings {
+                width: 8,
+                height: 8,
+                samples_per_pixel: 4,
+                integrator,
+                seed: Some(4),
+                ..RenderSettings::default()
+            });
+            let image = renderer.render(world.clone(), &camera);
+            for color in image.pixels() {
+                assert!(
+                    (color.luminance() - expected).abs() < 1e-6,
+                    "{}",
+                    color.luminance()
+                );
+            }
+        }
+        assert_eq!(
+            "bvh_cost".parse(),
+            Ok(IntegratorKind::Debug(DebugView::BvhCost))
+        );
+    }
+
+    #[test]
+    fn id_and_cost_views_follow_the_built_world() {
+        // the walls and boxes of the cornell box cost more to find than the
+        // open space around them, and its materials keep their colors
+        // however many threads render them
+        let scene = worlds::builtin("cornell_box", 0).unwrap();
+        let camera = scene.build_camera();
+        let render = |view, threads| {
+            let renderer = Renderer::new(RenderSettings {
+                width: 16,
+                height: 16,
+                samples_per_pixel: 1,
+                integrator: IntegratorKind::Debug(view),
+                threads,
+                seed: Some(4),
+                ..RenderSettings::default()
+            });
+            renderer.render(scene.world.clone(), &camera)
+        };
+        let cost = render(DebugView::BvhCost, 1);
+        assert!(cost.pixels().iter().any(|&c| c != cost.pixels()[0]));
+        let one = render(DebugView::MaterialId, 1);
+        let four = render(DebugView::MaterialId, 4);
+        assert!(one.pixels().iter().any(|&c| c != one.pixels()[0]));
+        assert!(one.pixels() == four.pixels());
+    }
+}
@@ -0,0 +1,28 @@
+pub mod accumulator;
+pub mod adaptive;
+pub mod aov;
+pub mod checkpoint;
+pub mod denoise;
+pub mod filter;
+pub mod framebuffer;
+pub mod integrator;
+pub mod output;
+pub mod renderer;
+pub mod tile;
+pub mod tonemap;
+
+pub use accumulator::Accumulator;
+pub use adaptive::{heatmap, AdaptiveSampling};
+pub use aov::{Aov, Aovs};
+pub use checkpoint::{load_checkpoint, save_checkpoint, Checkpoint, SceneId};
+pub use denoise::Denoiser;
+pub use filter::{Filter, FilterKind};
+pub use framebuffer::Framebuffer;
+pub use integrator::{
+    AmbientOcclusionIntegrator, DebugIntegrator, DebugView, DirectIntegrator, Integrator,
+    IntegratorKind, PathIntegrator,
+};
+pub use output::{save_image, save_with_aovs, ImageFormat, OutputError};
+pub use renderer::{Progress, RenderSettings, Renderer};
+pub use tile::{Tile, TileOrder};
+pub use tonemap::{PostProcess, ToneMap};
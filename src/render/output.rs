@@ -0,0 +1,279 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use exr::prelude::{
+    f16, AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, SmallVec,
+    Vec2, WritableImage,
+};
+
+use crate::data::Color;
+
+use super::aov::{Aov, Aovs};
+use super::{Framebuffer, PostProcess};
+
+// PNG goes through the post-processing settings to 8 bits; EXR and Radiance
+// HDR keep the linear radiance as it comes out of the renderer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Exr { half: bool },
+    Hdr,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "exr" => Some(ImageFormat::Exr { half: false }),
+            "hdr" | "pic" => Some(ImageFormat::Hdr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    UnknownFormat(PathBuf),
+    Io(io::Error),
+    Png(lodepng::Error),
+    Exr(exr::error::Error),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputError::UnknownFormat(path) => write!(
+                f,
+                "cannot tell the image format of {}, use .png, .exr or .hdr",
+                path.display()
+            ),
+            OutputError::Io(error) => write!(f, "{}", error),
+            OutputError::Png(error) => write!(f, "{}", error),
+            OutputError::Exr(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+pub fn save_image(
+    path: &Path,
+    framebuffer: &Framebuffer,
+    format: ImageFormat,
+    post: &PostProcess,
+) -> Result<(), OutputError> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    match format {
+        ImageFormat::Png => {
+            let buffer = post.to_rgba8(framebuffer);
+            lodepng::encode32_file(path, buffer.as_ref(), width, height).map_err(OutputError::Png)
+        }
+        ImageFormat::Exr { half } => {
+            let result = if half {
+                exr::prelude::write_rgb_file(path, width, height, |x, y| {
+                    let c = framebuffer.get(x, y);
+                    (
+                        f16::from_f64(c.x()),
+                        f16::from_f64(c.y()),
+                        f16::from_f64(c.z()),
+                    )
+                })
+            } else {
+                exr::prelude::write_rgb_file(path, width, height, |x, y| {
+                    let c = framebuffer.get(x, y);
+                    (c.x() as f32, c.y() as f32, c.z() as f32)
+                })
+            };
+            result.map_err(OutputError::Exr)
+        }
+        ImageFormat::Hdr => write_hdr(path, framebuffer).map_err(OutputError::Io),
+    }
+}
+
+// EXR keeps every AOV in the same file as extra channels (albedo.R,
+// normal.X, Z, object_id, ...); other formats get one file per AOV next to
+// the beauty image, named like out.albedo.png.
+pub fn save_with_aovs(
+    path: &Path,
+    framebuffer: &Framebuffer,
+    aovs: &Aovs,
+    selected: &[Aov],
+    format: ImageFormat,
+    post: &PostProcess,
+) -> Result<(), OutputError> {
+    match format {
+        ImageFormat::Exr { half } => {
+            write_exr_layers(path, framebuffer, aovs, selected, half).map_err(OutputError::Exr)
+        }
+        _ => {
+            save_image(path, framebuffer, format, post)?;
+            for &aov in selected {
+                let image = aovs.image(aov);
+                save_image(
+                    &aov_path(path, aov),
+                    &image,
+                    format,
+                    &PostProcess::default(),
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn aov_path(path: &Path, aov: Aov) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, aov.name(), extension.to_string_lossy()),
+        None => format!("{}.{}", stem, aov.name()),
+    };
+    path.with_file_name(name)
+}
+
+fn write_exr_layers(
+    path: &Path,
+    framebuffer: &Framebuffer,
+    aovs: &Aovs,
+    selected: &[Aov],
+    half: bool,
+) -> exr::error::UnitResult {
+    let floats = |name: &str, values: Vec<f64>| {
+        let samples = if half {
+            FlatSamples::F16(values.into_iter().map(f16::from_f64).collect())
+        } else {
+            FlatSamples::F32(values.into_iter().map(|x| x as f32).collect())
+        };
+        AnyChannel::new(name, samples)
+    };
+    let colors = |prefix: &str, names: [&str; 3], pixels: &[Color]| {
+        let mut channels = Vec::new();
+        for (index, name) in names.iter().enumerate() {
+            let values = pixels.iter().map(|c| [c.x(), c.y(), c.z()][index]);
+            channels.push(floats(&format!("{}{}", prefix, name), values.collect()));
+        }
+        channels
+    };
+
+    let mut channels = colors("", ["R", "G", "B"], framebuffer.pixels());
+    for &aov in selected {
+        match aov {
+            Aov::Albedo => channels.extend(colors("albedo.", ["R", "G", "B"], &aovs.albedo)),
+            Aov::Normal => channels.extend(colors("normal.", ["X", "Y", "Z"], &aovs.normal)),
+            Aov::Depth => channels.push(floats("Z", aovs.depth.clone())),
+            Aov::ObjectId => channels.push(AnyChannel::new(
+                "object_id",
+                FlatSamples::U32(aovs.object_id.clone()),
+            )),
+            Aov::MaterialId => channels.push(AnyChannel::new(
+                "material_id",
+                FlatSamples::U32(aovs.material_id.clone()),
+            )),
+        }
+    }
+
+    let size = Vec2(framebuffer.width(), framebuffer.height());
+    let layer = Layer::new(
+        size,
+        LayerAttributes::default(),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(channels)),
+    );
+    Image::from_layer(layer).write().to_file(path)
+}
+
+// Radiance RGBE: a shared exponent byte and three mantissa bytes per pixel,
+// written without run length encoding which every reader accepts.
+fn write_hdr(path: &Path, framebuffer: &Framebuffer) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        framebuffer.height(),
+        framebuffer.width()
+    )?;
+    for color in framebuffer.pixels() {
+        out.write_all(&rgbe(color))?;
+    }
+    out.flush()
+}
+
+fn rgbe(color: &Color) -> [u8; 4] {
+    let clean = |x: f64| if x.is_finite() { x.max(0.0) } else { 0.0 };
+    let (r, g, b) = (clean(color.x()), clean(color.y()), clean(color.z()));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0; 4];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / 2f64.powi(e) >= 1.0 {
+        e += 1;
+    }
+    let scale = 256.0 / 2f64.powi(e);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (e + 128).clamp(0, 255) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use exr::prelude::read_first_rgba_layer_from_file;
+
+    use crate::data::Color;
+    use crate::render::Framebuffer;
+
+    use super::{rgbe, save_image, ImageFormat, PostProcess};
+
+    #[test]
+    fn exr_round_trip() {
+        let pixels = vec![
+            Color::new(12.5, 0.25, 0.0),
+            Color::new(0.0, 1.0, 2.0),
+            Color::new(0.125, 0.5, 1000.0),
+            Color::new(3.0, 3.0, 3.0),
+        ];
+        let framebuffer = Framebuffer::from_pixels(2, 2, pixels.clone());
+        let dir = std::env::temp_dir().join("rust_tracer_output_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("round_trip.exr");
+        save_image(
+            &path,
+            &framebuffer,
+            ImageFormat::Exr { half: true },
+            &PostProcess::default(),
+        )
+        .unwrap();
+
+        let image = read_first_rgba_layer_from_file(
+            &path,
+            |size, _| vec![Color::zero(); size.0 * size.1],
+            |pixels: &mut Vec<Color>, position, (r, g, b, _): (f32, f32, f32, f32)| {
+                pixels[position.1 * 2 + position.0] = Color::new(r as f64, g as f64, b as f64)
+            },
+        )
+        .unwrap();
+        assert_eq!(image.layer_data.channel_data.pixels, pixels);
+    }
+
+    #[test]
+    fn rgbe_keeps_highlights() {
+        let decode = |p: [u8; 4]| {
+            let f = 2f64.powi(p[3] as i32 - 136);
+            Color::new(p[0] as f64 * f, p[1] as f64 * f, p[2] as f64 * f)
+        };
+        for &(r, g, b) in [(1.0, 0.5, 0.25), (37.5, 0.0, 3.0), (0.002, 0.004, 0.001)].iter() {
+            let c = decode(rgbe(&Color::new(r, g, b)));
+            let v: f64 = r.max(g).max(b);
+            assert!((c.x() - r).abs() <= v / 128.0);
+            assert!((c.y() - g).abs() <= v / 128.0);
+            assert!((c.z() - b).abs() <= v / 128.0);
+        }
+        assert_eq!(rgbe(&Color::new(0.0, -1.0, f64::NAN)), [0; 4]);
+    }
+}
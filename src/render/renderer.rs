@@ -0,0 +1,403 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::engine::{Background, Camera, Hittable, HittableList};
+use crate::util::rng::random_seed;
+use crate::util::sampler::SamplerKind;
+use crate::util::thread_pool::RTThreadPool;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use super::adaptive::AdaptiveSampling;
+use super::filter::Filter;
+use super::integrator::IntegratorKind;
+use super::tile::{tiles, TileBuffer, TileOrder};
+use super::tonemap::PostProcess;
+use super::{Accumulator, Framebuffer};
+
+#[derive(Clone)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    pub integrator: IntegratorKind,
+    pub background: Background,
+    pub threads: usize,
+    pub seed: Option<u64>,
+    pub tile_size: usize,
+    pub tile_order: TileOrder,
+    pub progressive: bool,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub sampler: SamplerKind,
+    pub filter: Filter,
+    pub post: PostProcess,
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> f64 {
+        self.width as f64 / self.height as f64
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            width: 800,
+            height: 450,
+            samples_per_pixel: 50,
+            max_depth: 50,
+            integrator: IntegratorKind::Path,
+            background: Background::Sky,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: None,
+            tile_size: 32,
+            tile_order: TileOrder::Spiral,
+            progressive: false,
+            adaptive: None,
+            sampler: SamplerKind::Sobol,
+            filter: Filter::default(),
+            post: PostProcess::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Progress {
+    Tile,
+    Pass,
+}
+
+pub struct Renderer {
+    settings: RenderSettings,
+    lights: Arc<HittableList>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Renderer {
+    pub fn new(mut settings: RenderSettings) -> Renderer {
+        assert!(settings.width > 0 && settings.height > 0);
+        assert!(settings.samples_per_pixel > 0);
+        assert!(settings.tile_size > 0);
+        assert!(settings.filter.radius > 0.0);
+        settings.seed = Some(settings.seed.unwrap_or_else(random_seed));
+        Renderer {
+            settings,
+            lights: Arc::new(HittableList::new()),
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Emitters to sample directly at every bounce besides finding them by
+    // chance. They must also be part of the world.
+    pub fn with_lights(mut self, lights: Arc<HittableList>) -> Renderer {
+        self.lights = lights;
+        self
+    }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    // Setting the flag makes workers skip the tiles they have not started
+    // yet, so `render_into` returns soon with a partial accumulator.
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.cancel)
+    }
+
+    pub fn render(&self, world: Arc<dyn Hittable + Send + Sync>, camera: &Camera) -> Framebuffer {
+        let mut accumulator = Accumulator::new(self.settings.width, self.settings.height);
+        self.render_into(world, camera, &mut accumulator, |_, _| {});
+        accumulator.resolve()
+    }
+
+    // Adds samples to `accumulator` until every pixel has the requested
+    // count, or has converged when sampling adaptively, picking up wherever
+    // it left off. Unless the settings ask for a progressive or adaptive
+    // render there is only one pass. Returns false if the render was
+    // cancelled before it finished.
+    pub fn render_into<F>(
+        &self,
+        world: Arc<dyn Hittable + Send + Sync>,
+        camera: &Camera,
+        accumulator: &mut Accumulator,
+        mut on_progress: F,
+    ) -> bool
+    where
+        F: FnMut(&Accumulator, Progress),
+    {
+        let RenderSettings {
+            width,
+            height,
+            samples_per_pixel,
+            threads,
+            tile_size,
+            tile_order,
+            adaptive,
+            filter,
+            ..
+        } = self.settings;
+        assert_eq!((accumulator.width(), accumulator.height()), (width, height));
+        let tiles = tiles(width, height, tile_size, tile_order);
+        let pool = RTThreadPool::new(threads);
+        let shade = Arc::new(self.shader(world, camera.clone()));
+
+        let bar = ProgressBar::new((width * height * samples_per_pixel) as u64);
+        bar.set_style(
+            ProgressStyle::default_bar().template("[{elapsed}|{eta}] {bar:60} {percent}%"),
+        );
+        let done: usize = accumulator
+            .samples()
+            .iter()
+            .map(|&n| n.min(samples_per_pixel))
+            .sum();
+        bar.set_position(done as u64);
+
+        for end in pass_ends(&self.settings) {
+            let buffers: Vec<_> = tiles
+                .iter()
+                .map(|&tile| {
+                    let mut buffer = accumulator.tile(tile, end, filter.margin());
+                    if let Some(adaptive) = adaptive {
+                        adaptive.stop_converged(&mut buffer);
+                    }
+                    buffer
+                })
+                .filter(|buffer| buffer.samples_left() > 0)
+                .collect();
+            if buffers.is_empty() {
+                continue;
+            }
+            let shade = Arc::clone(&shade);
+            pool.render(
+                buffers,
+                move |buffer| shade(buffer),
+                |buffer| {
+                    bar.inc(accumulator.write_tile(buffer) as u64);
+                    on_progress(accumulator, Progress::Tile);
+                },
+            );
+            if self.cancel.load(Ordering::SeqCst) {
+                break;
+            }
+            on_progress(accumulator, Progress::Pass);
+        }
+        bar.finish();
+
+        !self.cancel.load(Ordering::SeqCst)
+    }
+
+    // Adds the samples the buffer asks for to every pixel of a tile.
+    fn shader(
+        &self,
+        world: Arc<dyn Hittable + Send + Sync>,
+        camera: Camera,
+    ) -> impl Fn(&mut TileBuffer) + Send + Sync + 'static {
+        let RenderSettings {
+            width,
+            height,
+            samples_per_pixel,
+            max_depth,
+            integrator,
+            background,
+            seed,
+            sampler,
+            filter,
+            ..
+        } = self.settings;
+        let seed = seed.unwrap();
+        let integrator = integrator.integrator(max_depth);
+        let lights = Arc::clone(&self.lights);
+        let cancel = Arc::clone(&self.cancel);
+
+        move |buffer: &mut TileBuffer| {
+            if cancel.load(Ordering::SeqCst) {
+                return;
+            }
+            let mut sampler = sampler.sampler(seed, samples_per_pixel);
+            for k in 0..buffer.tile.len() {
+                let (x, y) = buffer.position(k);
+                let (i, j) = (x, height - 1 - y);
+                let pixel = (j * width + i) as u64;
+                for sample in buffer.counts[k]..buffer.targets[k] {
+                    sampler.start_sample(pixel, sample as u64);
+                    let (du, dv) = sampler.get_2d();
+                    let u = (i as f64 + du) / width as f64;
+                    let v = (j as f64 + dv) / height as f64;
+                    let ray = camera.get_ray(u, v, sampler.as_mut());
+                    let color = integrator.radiance(
+                        &ray,
+                        world.as_ref(),
+                        &lights,
+                        &background,
+                        sampler.as_mut(),
+                    );
+                    buffer.pixels[k] += color;
+                    buffer.squares[k] += color.luminance().powi(2);
+                    // framebuffer rows run top to bottom, v runs up
+                    let (sx, sy) = (x as f64 + du, y as f64 + 1.0 - dv);
+                    buffer.film.splat(&filter, sx, sy, color);
+                }
+                buffer.counts[k] = buffer.counts[k].max(buffer.targets[k]);
+            }
+        }
+    }
+}
+
+// Progressive passes double the sample count each time: 1, 2, 4, 8, ...
+// Adaptive renders do the same starting from their minimum, checking which
+// pixels have converged between passes.
+fn pass_ends(settings: &RenderSettings) -> Vec<usize> {
+    let samples_per_pixel = settings.samples_per_pixel;
+    let first = match (settings.progressive, settings.adaptive) {
+        (true, _) => 1,
+        (false, Some(adaptive)) => adaptive.min_samples.clamp(1, samples_per_pixel),
+        (false, None) => return vec![samples_per_pixel],
+    };
+    let mut ends = vec![first];
+    while ends[ends.len() - 1] < samples_per_pixel {
+        ends.push((2 * ends[ends.len() - 1]).min(samples_per_pixel));
+    }
+    ends
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::data::worlds::{cornell_box, cornell_box_lamp, three_balls};
+    use crate::data::Color;
+    use crate::data::Point3;
+    use crate::engine::{Background, CameraConfig, HittableList};
+    use crate::render::{Accumulator, Filter, FilterKind};
+
+    use super::{RenderSettings, Renderer, TileOrder};
+
+    #[test]
+    fn seed_is_independent_of_threads() {
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples_per_pixel: 5,
+            seed: Some(11),
+            ..RenderSettings::default()
+        };
+        let camera = CameraConfig {
+            aperture: 0.1,
+            ..CameraConfig::default()
+        }
+        .build(settings.aspect_ratio());
+        let render = |threads, tile_size, tile_order, progressive| {
+            let renderer = Renderer::new(RenderSettings {
+                threads,
+                tile_size,
+                tile_order,
+                progressive,
+                ..settings.clone()
+            });
+            renderer.render(three_balls(), &camera)
+        };
+
+        let single = render(1, 32, TileOrder::Scanline, false);
+        let many = render(4, 5, TileOrder::Spiral, false);
+        let progressive = render(3, 8, TileOrder::Spiral, true);
+        assert_eq!(single.pixels(), many.pixels());
+        assert_eq!(single.pixels(), progressive.pixels());
+    }
+
+    #[test]
+    fn one_pixel_wide_images_render() {
+        for &(width, height) in [(1, 1), (1, 3), (3, 1)].iter() {
+            let settings = RenderSettings {
+                width,
+                height,
+                samples_per_pixel: 2,
+                seed: Some(1),
+                ..RenderSettings::default()
+            };
+            let camera = CameraConfig::default().build(settings.aspect_ratio());
+            let image = Renderer::new(settings).render(three_balls(), &camera);
+            assert!(image
+                .pixels()
+                .iter()
+                .all(|c| c.x().is_finite() && c.y().is_finite() && c.z().is_finite()));
+        }
+    }
+
+    #[test]
+    fn wide_filters_splat_across_tiles() {
+        let settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples_per_pixel: 4,
+            seed: Some(7),
+            tile_size: 5,
+            ..RenderSettings::default()
+        };
+        let camera = CameraConfig::default().build(settings.aspect_ratio());
+        let gray = Color::new(0.25, 0.5, 0.75);
+        for &kind in [FilterKind::Tent, FilterKind::Gaussian, FilterKind::Lanczos].iter() {
+            let filter = Filter::new(kind);
+            // negative lobes and the image border must still average to the
+            // color of a featureless image
+            let flat = Renderer::new(RenderSettings {
+                background: Background::Solid(gray),
+                filter,
+                ..settings.clone()
+            })
+            .render(Arc::new(HittableList::new()), &camera);
+            assert!(flat
+                .pixels()
+                .iter()
+                .all(|&color| (color - gray).len() < 1e-9));
+
+            let render = |threads| {
+                Renderer::new(RenderSettings {
+                    threads,
+                    filter,
+                    ..settings.clone()
+                })
+                .render(three_balls(), &camera)
+            };
+            assert_eq!(render(1).pixels(), render(4).pixels());
+        }
+    }
+
+    #[test]
+    fn sampling_lights_converges_faster_to_the_same_image() {
+        let settings = RenderSettings {
+            width: 16,
+            height: 16,
+            samples_per_pixel: 64,
+            max_depth: 2,
+            seed: Some(2),
+            background: Background::Solid(Color::zero()),
+            ..RenderSettings::default()
+        };
+        let camera = CameraConfig {
+            look_from: Point3::new(278.0, 278.0, -800.0),
+            // keeps the lamp out of view, its edges are noisy either way
+            look_at: Point3::new(278.0, 100.0, 0.0),
+            vfov: 40.0,
+            ..CameraConfig::default()
+        }
+        .build(1.0);
+        let mut lights = HittableList::new();
+        lights.add(cornell_box_lamp());
+        let render = |renderer: Renderer| {
+            let mut accumulator = Accumulator::new(16, 16);
+            renderer.render_into(cornell_box(), &camera, &mut accumulator, |_, _| {});
+            let mean = |values: Vec<f64>| values.iter().sum::<f64>() / values.len() as f64;
+            let image = accumulator.resolve();
+            let brightness = mean(image.pixels().iter().map(|c| c.luminance()).collect());
+            (brightness, mean(accumulator.variance()))
+        };
+
+        let (plain, plain_noise) = render(Renderer::new(settings.clone()));
+        let (sampled, sampled_noise) =
+            render(Renderer::new(settings).with_lights(Arc::new(lights)));
+        // within three standard errors of the noisy image's mean
+        assert!((sampled - plain).abs() < 3.0 * (plain_noise / 256.0).sqrt());
+        assert!(sampled_noise < plain_noise / 100.0);
+    }
+}
@@ -0,0 +1,218 @@
+use std::str::FromStr;
+
+use crate::data::Color;
+
+use super::filter::Filter;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    pub fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // copies the tile out of a whole image `width` pixels wide, rows top to
+    // bottom
+    pub fn read<T: Copy>(&self, image: &[T], width: usize) -> Vec<T> {
+        let mut values = Vec::with_capacity(self.len());
+        for y in self.y..self.y + self.height {
+            let start = y * width + self.x;
+            values.extend_from_slice(&image[start..start + self.width]);
+        }
+        values
+    }
+
+    pub fn write<T: Copy>(&self, image: &mut [T], width: usize, values: &[T]) {
+        for (row, line) in values.chunks(self.width).enumerate() {
+            let start = (self.y + row) * width + self.x;
+            image[start..start + self.width].copy_from_slice(line);
+        }
+    }
+
+    // the tile grown by `margin` pixels on every side, clipped to the image
+    pub fn expand(&self, margin: usize, width: usize, height: usize) -> Tile {
+        let (x, y) = (self.x.saturating_sub(margin), self.y.saturating_sub(margin));
+        Tile {
+            x,
+            y,
+            width: (self.x + self.width + margin).min(width) - x,
+            height: (self.y + self.height + margin).min(height) - y,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TileOrder {
+    Scanline,
+    Spiral,
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TileOrder, String> {
+        match s {
+            "scanline" => Ok(TileOrder::Scanline),
+            "spiral" => Ok(TileOrder::Spiral),
+            _ => Err(format!("unknown tile order '{}'", s)),
+        }
+    }
+}
+
+// Filter weighted sums of the samples taken in one tile, over `area`: the
+// tile and the margin around it a wide filter spreads them into. Every tile
+// keeps its own, so workers never share pixels, and they are only added up
+// when the image is resolved.
+#[derive(Clone)]
+pub struct FilmTile {
+    pub tile: Tile,
+    pub area: Tile,
+    pub weighted: Vec<Color>,
+    pub weights: Vec<f64>,
+}
+
+impl FilmTile {
+    pub fn new(tile: Tile, area: Tile) -> FilmTile {
+        FilmTile {
+            tile,
+            area,
+            weighted: vec![Color::zero(); area.len()],
+            weights: vec![0.0; area.len()],
+        }
+    }
+
+    // adds a sample taken at framebuffer position (x, y), in pixels, to the
+    // pixels whose centers are within the filter's reach
+    pub fn splat(&mut self, filter: &Filter, x: f64, y: f64, color: Color) {
+        let reach = |s: f64, start: usize, len: usize| {
+            let first = ((s - 0.5 - filter.radius).ceil() as i64).max(start as i64);
+            let last = ((s - 0.5 + filter.radius).floor() as i64).min((start + len) as i64 - 1);
+            first..=last
+        };
+        let area = self.area;
+        for qy in reach(y, area.y, area.height) {
+            for qx in reach(x, area.x, area.width) {
+                let weight = filter.evaluate(x - qx as f64 - 0.5, y - qy as f64 - 0.5);
+                if weight != 0.0 {
+                    let k = (qy as usize - area.y) * area.width + (qx as usize - area.x);
+                    self.weighted[k] += color * weight;
+                    self.weights[k] += weight;
+                }
+            }
+        }
+    }
+}
+
+// Pixels of one tile, rows top to bottom, filled by a worker and copied
+// into the framebuffer once the whole tile is done. `squares` sums the
+// squared luminance of every sample and `film` their filtered splats. The
+// worker takes samples `counts[k]` up to `targets[k]` for pixel k and then
+// updates the count.
+pub struct TileBuffer {
+    pub tile: Tile,
+    pub pixels: Vec<Color>,
+    pub squares: Vec<f64>,
+    pub counts: Vec<usize>,
+    pub targets: Vec<usize>,
+    pub film: FilmTile,
+}
+
+impl TileBuffer {
+    pub fn new(tile: Tile) -> TileBuffer {
+        TileBuffer {
+            tile,
+            pixels: vec![Color::zero(); tile.len()],
+            squares: vec![0.0; tile.len()],
+            counts: vec![0; tile.len()],
+            targets: vec![0; tile.len()],
+            film: FilmTile::new(tile, tile),
+        }
+    }
+
+    // framebuffer coordinates of pixel k
+    pub fn position(&self, k: usize) -> (usize, usize) {
+        (
+            self.tile.x + k % self.tile.width,
+            self.tile.y + k / self.tile.width,
+        )
+    }
+
+    pub fn samples_left(&self) -> usize {
+        self.counts
+            .iter()
+            .zip(&self.targets)
+            .map(|(&count, &target)| target.saturating_sub(count))
+            .sum()
+    }
+}
+
+pub fn tiles(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+    assert!(size > 0);
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+    let tile = |column: usize, row: usize| Tile {
+        x: column * size,
+        y: row * size,
+        width: size.min(width - column * size),
+        height: size.min(height - row * size),
+    };
+
+    match order {
+        TileOrder::Scanline => (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| tile(column, row))
+            .collect(),
+        TileOrder::Spiral => {
+            let total = columns * rows;
+            let mut result = Vec::with_capacity(total);
+            let (mut column, mut row) = ((columns as i64 - 1) / 2, (rows as i64 - 1) / 2);
+            let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+            let mut leg = 0;
+            while result.len() < total {
+                let (dx, dy) = directions[leg % 4];
+                for _ in 0..leg / 2 + 1 {
+                    if (0..columns as i64).contains(&column) && (0..rows as i64).contains(&row) {
+                        result.push(tile(column as usize, row as usize));
+                    }
+                    column += dx;
+                    row += dy;
+                }
+                leg += 1;
+            }
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tiles, TileOrder};
+
+    #[test]
+    fn tiles_cover_image_once() {
+        for &order in [TileOrder::Scanline, TileOrder::Spiral].iter() {
+            let (width, height) = (70, 33);
+            let mut covered = vec![0; width * height];
+            for tile in tiles(width, height, 16, order) {
+                for y in tile.y..tile.y + tile.height {
+                    for x in tile.x..tile.x + tile.width {
+                        covered[y * width + x] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|&n| n == 1));
+        }
+        let spiral = tiles(48, 48, 16, TileOrder::Spiral);
+        assert_eq!((spiral[0].x, spiral[0].y), (16, 16));
+    }
+}
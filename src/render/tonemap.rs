@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::data::Color;
+use crate::util::rng::sample_rng;
+
+use super::Framebuffer;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+impl FromStr for ToneMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ToneMap, String> {
+        match s {
+            "clamp" => Ok(ToneMap::Clamp),
+            "reinhard" => Ok(ToneMap::Reinhard),
+            "aces" => Ok(ToneMap::Aces),
+            _ => Err(format!("unknown tone mapping operator '{}'", s)),
+        }
+    }
+}
+
+impl ToneMap {
+    pub fn apply(&self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        match self {
+            ToneMap::Clamp => x.min(1.0),
+            ToneMap::Reinhard => x / (1.0 + x),
+            // Narkowicz's fit of the ACES filmic curve
+            ToneMap::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+// Turns linear radiance into 8-bit sRGB: exposure, tone mapping, the sRGB
+// transfer function and, optionally, dithering before quantization.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostProcess {
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub dither: bool,
+}
+
+impl Default for PostProcess {
+    fn default() -> PostProcess {
+        PostProcess {
+            exposure: 0.0,
+            tone_map: ToneMap::Clamp,
+            dither: false,
+        }
+    }
+}
+
+pub fn srgb_oetf(x: f64) -> f64 {
+    if x <= 0.003_130_8 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl PostProcess {
+    // display referred color in [0, 1], still linear
+    pub fn tone_map(&self, color: &Color) -> Color {
+        let scale = 2f64.powf(self.exposure);
+        let map = |x: f64| {
+            let x = if x.is_nan() { 0.0 } else { x * scale };
+            self.tone_map.apply(x)
+        };
+        Color::new(map(color.x()), map(color.y()), map(color.z()))
+    }
+
+    pub fn to_rgba8(&self, framebuffer: &Framebuffer) -> Vec<[u8; 4]> {
+        framebuffer
+            .pixels()
+            .iter()
+            .enumerate()
+            .map(|(i, color)| {
+                let color = self.tone_map(color);
+                // triangular noise of one step spreads banding into grain
+                let noise = if self.dither {
+                    let mut rng = sample_rng(0, i as u64, 0);
+                    rng.gen::<f64>() - rng.gen::<f64>()
+                } else {
+                    0.0
+                };
+                let quantize =
+                    |x: f64| (srgb_oetf(x) * 255.0 + noise).round().clamp(0.0, 255.0) as u8;
+                [
+                    quantize(color.x()),
+                    quantize(color.y()),
+                    quantize(color.z()),
+                    255,
+                ]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Color;
+    use crate::render::Framebuffer;
+
+    use super::{srgb_oetf, PostProcess, ToneMap};
+
+    #[test]
+    fn tone_maps_and_encodes() {
+        assert_eq!(srgb_oetf(0.0), 0.0);
+        assert!((srgb_oetf(1.0) - 1.0).abs() < 1e-12);
+        assert!((srgb_oetf(0.18) - 0.4614).abs() < 1e-4);
+
+        for &op in [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::Aces].iter() {
+            let mut last = 0.0;
+            for i in 0..100 {
+                let y = op.apply(i as f64 * 0.25);
+                assert!(y >= last && y <= 1.0);
+                last = y;
+            }
+        }
+
+        let framebuffer = Framebuffer::from_pixels(
+            2,
+            1,
+            vec![Color::new(0.25, 0.5, 4.0), Color::new(-1.0, 0.0, 1.0)],
+        );
+        let post = PostProcess {
+            exposure: 1.0,
+            ..PostProcess::default()
+        };
+        assert_eq!(
+            post.to_rgba8(&framebuffer),
+            vec![[188, 255, 255, 255], [0, 0, 255, 255]]
+        );
+    }
+}
@@ -1 +1,3 @@
+pub mod rng;
+pub mod sampler;
 pub mod thread_pool;
@@ -0,0 +1,25 @@
+use rand::SeedableRng;
+
+pub type RtRng = rand::rngs::StdRng;
+
+// splitmix64 finalizer, spreads nearby inputs over the whole seed space
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+pub fn seeded_rng(seed: u64) -> RtRng {
+    RtRng::seed_from_u64(mix(seed))
+}
+
+// Every sample of every pixel gets its own stream, so the result does not
+// depend on which thread renders it or in which order.
+pub fn sample_rng(seed: u64, pixel: u64, sample: u64) -> RtRng {
+    RtRng::seed_from_u64(mix(mix(mix(seed) ^ pixel) ^ sample))
+}
+
+pub fn random_seed() -> u64 {
+    rand::random()
+}
@@ -0,0 +1,421 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::data::Vec3;
+
+use super::rng::{mix, sample_rng, RtRng};
+
+// Hands out the random numbers of one camera sample. Every `get_1d` or
+// `get_2d` call moves on to the next dimension(s), so making the same calls
+// in the same order after `start_sample` gives the same numbers, no matter
+// which samples were taken before.
+pub trait Sampler {
+    fn start_sample(&mut self, pixel: u64, index: u64);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplerKind {
+    Random,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SamplerKind, String> {
+        match s {
+            "random" => Ok(SamplerKind::Random),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!("unknown sampler '{}'", s)),
+        }
+    }
+}
+
+impl SamplerKind {
+    // Stratified sampling divides every dimension among `samples_per_pixel`
+    // samples; the other samplers do not need to know the count.
+    pub fn sampler(&self, seed: u64, samples_per_pixel: usize) -> Box<dyn Sampler> {
+        let sequence = Sequence {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        };
+        match self {
+            SamplerKind::Random => Box::new(RandomSampler {
+                seed,
+                rng: sample_rng(seed, 0, 0),
+            }),
+            SamplerKind::Stratified => Box::new(StratifiedSampler {
+                sequence,
+                samples_per_pixel: samples_per_pixel.max(1) as u32,
+            }),
+            SamplerKind::Halton => Box::new(HaltonSampler { sequence }),
+            SamplerKind::Sobol => Box::new(SobolSampler { sequence }),
+        }
+    }
+}
+
+// Independent uniform numbers, the same stream per sample as before there
+// were samplers.
+pub struct RandomSampler {
+    seed: u64,
+    rng: RtRng,
+}
+
+impl Sampler for RandomSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.rng = sample_rng(self.seed, pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.gen()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.rng.gen(), self.rng.gen())
+    }
+}
+
+// Where the deterministic samplers are in their sequence. Each dimension of
+// each pixel gets its own hash, which decorrelates dimensions and
+// neighbouring pixels from each other.
+struct Sequence {
+    seed: u64,
+    pixel: u64,
+    index: u64,
+    dimension: u64,
+}
+
+impl Sequence {
+    fn start(&mut self, pixel: u64, index: u64) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next(&mut self, dimensions: u64) -> (u64, u64) {
+        let dimension = self.dimension;
+        self.dimension += dimensions;
+        let hash = mix(mix(mix(self.seed) ^ self.pixel) ^ dimension);
+        (dimension, hash)
+    }
+}
+
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn u32_to_unit(bits: u32) -> f64 {
+    bits as f64 / (1u64 << 32) as f64
+}
+
+// Element i of a random permutation of 0..n chosen by `seed`, without
+// building the permutation (Kensler, "Correlated Multi-Jittered Sampling").
+fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            return (i.wrapping_add(seed)) % n;
+        }
+    }
+}
+
+// Jittered samples in strata that are handed out in a random order per
+// pixel and dimension, so any set of dimensions stays uncorrelated.
+pub struct StratifiedSampler {
+    sequence: Sequence,
+    samples_per_pixel: u32,
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.sequence.start(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (_, hash) = self.sequence.next(1);
+        let n = self.samples_per_pixel;
+        let index = (self.sequence.index % n as u64) as u32;
+        let stratum = permutation_element(index, n, hash as u32);
+        let jitter = to_unit(mix(hash ^ self.sequence.index));
+        (stratum as f64 + jitter) / n as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (_, hash) = self.sequence.next(2);
+        let columns = (self.samples_per_pixel as f64).sqrt().ceil() as u32;
+        let rows = self.samples_per_pixel.div_ceil(columns);
+        let index = (self.sequence.index % (columns * rows) as u64) as u32;
+        let stratum = permutation_element(index, columns * rows, hash as u32);
+        let jitter = mix(hash ^ self.sequence.index);
+        (
+            ((stratum % columns) as f64 + to_unit(jitter)) / columns as f64,
+            ((stratum / columns) as f64 + to_unit(mix(jitter))) / rows as f64,
+        )
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+// The digits of `index` in `base`, mirrored around the decimal point, with
+// every digit shuffled by a permutation that depends on the digits before
+// it. Without the shuffling large bases put the first samples into a thin
+// slice of [0, 1).
+fn scrambled_radical_inverse(base: u64, mut index: u64, hash: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut scale = 1.0;
+    let mut reversed = 0;
+    // keep going after the last nonzero digit so the zeros get shuffled too
+    while 1.0 - scale * inverse_base < 1.0 {
+        let digit = index % base;
+        let seed = mix(hash ^ reversed) as u32;
+        reversed = reversed * base + permutation_element(digit as u32, base as u32, seed) as u64;
+        scale *= inverse_base;
+        index /= base;
+    }
+    (reversed as f64 * scale).min(1.0 - f64::EPSILON / 2.0)
+}
+
+// The Halton sequence, dimension d using the d-th prime as its base, Owen
+// scrambled per pixel and dimension. Dimensions past the prime table fall
+// back to independent random numbers.
+pub struct HaltonSampler {
+    sequence: Sequence,
+}
+
+impl HaltonSampler {
+    fn sample(&mut self) -> f64 {
+        let (dimension, hash) = self.sequence.next(1);
+        let index = self.sequence.index;
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => scrambled_radical_inverse(base, index, hash),
+            None => to_unit(mix(hash ^ index)),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.sequence.start(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.sample()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.sample(), self.sample())
+    }
+}
+
+// Direction numbers of the second Sobol dimension; the first one is the
+// identity.
+const SOBOL_1: [u32; 32] = {
+    let mut v = [0; 32];
+    v[0] = 1 << 31;
+    let mut i = 1;
+    while i < 32 {
+        v[i] = v[i - 1] ^ (v[i - 1] >> 1);
+        i += 1;
+    }
+    v
+};
+
+fn sobol_1(index: u32) -> u32 {
+    let mut x = 0;
+    for (bit, direction) in SOBOL_1.iter().enumerate() {
+        if (index >> bit) & 1 != 0 {
+            x ^= direction;
+        }
+    }
+    x
+}
+
+// Hash based Owen scrambling of a 32 bit fixed point fraction (Laine and
+// Karras, with the constants of Vegdahl's improved hash).
+fn owen_scramble(mut v: u32, seed: u32) -> u32 {
+    v = v.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20_adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x0552_6c56);
+    v ^= v.wrapping_mul(0x53a2_2864);
+    v.reverse_bits()
+}
+
+// The first two Sobol dimensions, Owen scrambled and shuffled again for
+// every pair of dimensions (Burley, "Practical Hash-based Owen Scrambling").
+// Scrambling the index the same way keeps every power of two run of samples
+// a (0, m, 2)-net, so progressive and adaptive passes stay well spread.
+pub struct SobolSampler {
+    sequence: Sequence,
+}
+
+impl SobolSampler {
+    fn index(&self, hash: u64) -> u32 {
+        owen_scramble(self.sequence.index as u32, hash as u32)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: u64, index: u64) {
+        self.sequence.start(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (_, hash) = self.sequence.next(1);
+        let x = self.index(hash).reverse_bits();
+        u32_to_unit(owen_scramble(x, (hash >> 32) as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (_, hash) = self.sequence.next(2);
+        let index = self.index(hash);
+        let (x, y) = (index.reverse_bits(), sobol_1(index));
+        (
+            u32_to_unit(owen_scramble(x, (hash >> 32) as u32)),
+            u32_to_unit(owen_scramble(y, mix(hash) as u32)),
+        )
+    }
+}
+
+// Uniform point in the unit disk in the xy plane. The concentric mapping
+// (Shirley and Chiu) keeps nearby samples nearby, unlike rejection.
+pub fn sample_disk(u: (f64, f64)) -> Vec3 {
+    let (x, y) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if x == 0.0 && y == 0.0 {
+        return Vec3::zero();
+    }
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, PI / 4.0 * (y / x))
+    } else {
+        (y, PI / 2.0 - PI / 4.0 * (x / y))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// uniform direction
+pub fn sample_sphere(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// direction above the xy plane with density proportional to its cosine
+// with the z axis, by lifting a point in the disk onto the hemisphere
+pub fn sample_cosine_hemisphere(u: (f64, f64)) -> Vec3 {
+    let d = sample_disk(u);
+    let z = (1.0 - d.x() * d.x() - d.y() * d.y()).max(0.0).sqrt();
+    Vec3::new(d.x(), d.y(), z)
+}
+
+// uniform direction within `cos_max` of the z axis
+pub fn sample_cone(u: (f64, f64), cos_max: f64) -> Vec3 {
+    let z = 1.0 - u.0 * (1.0 - cos_max);
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// uniform point inside the unit ball; u picks the direction and u_r, uniform
+// in [0, 1), the cube of the distance from the centre
+pub fn sample_ball(u: (f64, f64), u_r: f64) -> Vec3 {
+    u_r.cbrt() * sample_sphere(u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SamplerKind;
+
+    const KINDS: [SamplerKind; 4] = [
+        SamplerKind::Random,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ];
+
+    #[test]
+    fn samples_depend_only_on_pixel_and_index() {
+        for &kind in KINDS.iter() {
+            let mut first = kind.sampler(7, 16);
+            first.start_sample(5, 3);
+            let expected: Vec<f64> = (0..100).map(|_| first.get_1d()).collect();
+            assert!(expected.iter().all(|x| (0.0..1.0).contains(x)));
+
+            let mut second = kind.sampler(7, 16);
+            second.start_sample(6, 9);
+            second.get_2d();
+            second.start_sample(5, 3);
+            let got: Vec<f64> = (0..100).map(|_| second.get_1d()).collect();
+            assert_eq!(got, expected);
+        }
+    }
+
+    #[test]
+    fn sixteen_samples_fill_every_stratum() {
+        for &kind in [SamplerKind::Stratified, SamplerKind::Sobol].iter() {
+            let mut sampler = kind.sampler(1, 16);
+            for pixel in 0..4 {
+                // the first pair of dimensions and one further along
+                for &skip in [0, 3].iter() {
+                    let mut cells = [0; 16];
+                    for index in 0..16 {
+                        sampler.start_sample(pixel, index);
+                        for _ in 0..skip {
+                            sampler.get_2d();
+                        }
+                        let (x, y) = sampler.get_2d();
+                        cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] += 1;
+                    }
+                    assert_eq!(cells, [1; 16]);
+                }
+            }
+        }
+
+        let mut halton = SamplerKind::Halton.sampler(1, 16);
+        let mut cells = [0; 16];
+        for index in 0..16 {
+            halton.start_sample(2, index);
+            cells[(halton.get_1d() * 16.0) as usize] += 1;
+        }
+        assert_eq!(cells, [1; 16]);
+    }
+}
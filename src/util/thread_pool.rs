@@ -1,138 +1,89 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
-use std::sync::Mutex;
 use std::thread;
 
-use crossbeam_channel::bounded;
 use crossbeam_channel::unbounded;
 use crossbeam_channel::{Receiver, Sender};
-use indicatif::ProgressBar;
-use indicatif::ProgressStyle;
-use rand;
-use rand::SeedableRng;
-
-type Pixel = [u8; 4];
-type ResultMessage = Result<PlacedPixel, PlacedPixelErr>;
-
-pub struct PlacedPixel {
-    pub i: usize,
-    pub j: usize,
-    pub color: Pixel,
-}
 
-pub struct RTThreadPool {
-    workers: Vec<Worker>,
-    sender: Sender<Message>,
-    receiver: Option<Receiver<ResultMessage>>,
-    collect_handle: Option<thread::JoinHandle<()>>,
-    bar: Arc<ProgressBar>,
-    pub end_image: Arc<Mutex<Vec<[u8; 4]>>>,
-    height: usize,
-    width: usize,
-}
+type Shader<B> = dyn Fn(&mut B) + Send + Sync;
 
-type JobFn = dyn FnMut(Arc<Mutex<rand::rngs::StdRng>>) -> ResultMessage + Send + 'static;
-type Job = Box<JobFn>;
+// a finished buffer, or what the shader panicked with
+type Finished<B> = Result<B, Box<dyn Any + Send>>;
 
-enum Message {
-    NewJob(Job),
+enum Message<B> {
+    Render(B, Arc<Shader<B>>),
     Terminate,
 }
 
-pub struct PlacedPixelErr {
-    i: usize,
-    j: usize,
+// Workers take buffers (usually one per tile) from a shared queue, fill
+// them in with the shader they came with and send them back.
+pub struct RTThreadPool<B> {
+    workers: Vec<Worker>,
+    sender: Sender<Message<B>>,
+    // the workers' end of the queue, to drop what is left after a panic
+    queue: Receiver<Message<B>>,
+    receiver: Receiver<Finished<B>>,
 }
 
-impl RTThreadPool {
-    pub fn new(size: usize, width: usize, height: usize) -> RTThreadPool {
+impl<B: Send + 'static> RTThreadPool<B> {
+    pub fn new(size: usize) -> RTThreadPool<B> {
         assert!(size > 0);
-        let (thread_sender, my_receiver) = unbounded();
-        let (my_sender, thread_receiver) = bounded(size);
-        let thread_receiver = Arc::new(Mutex::new(thread_receiver));
-        let thread_sender = Arc::new(thread_sender);
-        let mut workers = Vec::with_capacity(size);
-        let bar = Arc::new(ProgressBar::new(height as u64));
-        bar.set_style(
-            ProgressStyle::default_bar().template("[{elapsed}|{eta}] {bar:60} {pos}/{len}"),
-        );
-
-        for id in 0..size {
-            workers.push(Worker::new(
-                id,
-                Arc::clone(&thread_receiver),
-                Arc::clone(&thread_sender),
-            ));
-        }
-
-        let end_image = Arc::new(Mutex::new(vec![[0, 0, 0, 255]; width * height]));
+        let (sender, thread_receiver) = unbounded();
+        let (thread_sender, receiver) = unbounded();
+        let workers = (0..size)
+            .map(|id| Worker::new(id, thread_receiver.clone(), thread_sender.clone()))
+            .collect();
 
         RTThreadPool {
             workers,
-            sender: my_sender,
-            receiver: Some(my_receiver),
-            end_image,
-            width,
-            height,
-            collect_handle: None,
-            bar,
+            sender,
+            queue: thread_receiver,
+            receiver,
         }
     }
 
-    pub fn execute<F>(&self, f: F)
+    // Queues every buffer at once and hands finished ones to `done` as they
+    // come in, in whatever order the workers finish them. A panic in the
+    // shader is raised again here once it reaches the queue.
+    pub fn render<S, D>(&self, buffers: Vec<B>, shader: S, mut done: D)
     where
-        F: FnMut(Arc<Mutex<rand::rngs::StdRng>>) -> ResultMessage + Send + 'static,
+        S: Fn(&mut B) + Send + Sync + 'static,
+        D: FnMut(B),
     {
-        let job = Box::new(f);
-        self.sender.send(Message::NewJob(job)).unwrap();
-    }
-
-    pub fn start_collecting(&mut self) {
-        let receiver_handle = self.receiver.take().unwrap();
-        let image_ref = Arc::clone(&self.end_image);
-        let height = self.height;
-        let width = self.width;
-        let total_pixels = width * height;
-        let bar_ref = Arc::clone(&self.bar);
-
-        self.collect_handle = Some(thread::spawn(move || {
-            let mut pixels_placed = 0;
-            while pixels_placed < total_pixels {
-                let message = receiver_handle.recv().unwrap();
-                match message {
-                    Ok(pixel) => {
-                        let offset = (height - 1 - pixel.j) * width + pixel.i;
-                        image_ref.lock().unwrap()[offset] = pixel.color;
+        let shader: Arc<Shader<B>> = Arc::new(shader);
+        let count = buffers.len();
+        for buffer in buffers {
+            self.sender
+                .send(Message::Render(buffer, Arc::clone(&shader)))
+                .unwrap();
+        }
+        for received in 1..=count {
+            match self.receiver.recv().unwrap() {
+                Ok(buffer) => done(buffer),
+                Err(payload) => {
+                    // skip the buffers nobody has started and wait for the
+                    // rest, so the pool can be used again
+                    let mut pending = count - received;
+                    while self.queue.try_recv().is_ok() {
+                        pending -= 1;
                     }
-                    Err(e) => {
-                        println!("Failed to shoot ray at {} {}", e.i, e.j);
+                    for _ in 0..pending {
+                        let _ = self.receiver.recv();
                     }
-                }
-                pixels_placed += 1;
-                if pixels_placed % width == 0 {
-                    bar_ref.inc(1);
+                    panic::resume_unwind(payload)
                 }
             }
-        }));
-    }
-
-    pub fn collect(&mut self) {
-        self.collect_handle.take().unwrap().join().unwrap();
-        self.bar.finish();
+        }
     }
 }
 
-impl Drop for RTThreadPool {
+impl<B> Drop for RTThreadPool<B> {
     fn drop(&mut self) {
-        println!("Sending terminate message to all workers");
-
         for _ in &self.workers {
             self.sender.send(Message::Terminate).unwrap();
         }
-
-        println!("Shutting down workers!");
-
         for worker in &mut self.workers {
-            println!("Shutting down worker {}", worker.id);
             if let Some(thread) = worker.thread.take() {
                 thread.join().unwrap();
             }
@@ -141,34 +92,58 @@ impl Drop for RTThreadPool {
 }
 
 struct Worker {
-    id: usize,
     thread: Option<thread::JoinHandle<()>>,
 }
 
 impl Worker {
-    fn new(
+    fn new<B: Send + 'static>(
         id: usize,
-        receiver: Arc<Mutex<Receiver<Message>>>,
-        sender: Arc<Sender<ResultMessage>>,
+        receiver: Receiver<Message<B>>,
+        sender: Sender<Finished<B>>,
     ) -> Worker {
-        let rng = Arc::new(Mutex::new(rand::rngs::StdRng::from_entropy()));
-        let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv().unwrap();
-            match message {
-                Message::NewJob(mut job) => {
-                    let result = job(Arc::clone(&rng));
-                    sender.send(result).unwrap();
-                }
-                Message::Terminate => {
-                    println!("Worker {} was told to terminate", id);
-                    break;
+        let thread = thread::Builder::new()
+            .name(format!("worker-{}", id))
+            .spawn(move || {
+                while let Ok(Message::Render(mut buffer, shader)) = receiver.recv() {
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| shader(&mut buffer)));
+                    if sender.send(result.map(|_| buffer)).is_err() {
+                        break;
+                    }
                 }
-            }
-        });
+            })
+            .unwrap();
 
         Worker {
-            id,
             thread: Some(thread),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::RTThreadPool;
+
+    #[test]
+    fn shader_panics_reach_the_caller() {
+        let pool = RTThreadPool::new(2);
+        let mut sum = 0;
+        pool.render((0..10).collect(), |x: &mut u32| *x *= 2, |x| sum += x);
+        assert_eq!(sum, 90);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.render(
+                (0..10).collect(),
+                |x: &mut u32| assert!(*x != 7, "bad tile"),
+                |_| {},
+            )
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"bad tile"));
+
+        let mut sum = 0;
+        pool.render((0..10).collect(), |x: &mut u32| *x += 1, |x| sum += x);
+        assert_eq!(sum, 55);
+    }
+}